@@ -0,0 +1,107 @@
+use proc_macro2::TokenStream;
+
+use quote::quote;
+use syn::parse::Error;
+use syn::visit_mut::VisitMut;
+
+/// The arguments of the `#[auto_upcast(...)]` attribute.
+#[derive(Debug)]
+pub struct AutoUpcastArgs {
+    /// The path of the powerset-enum crate, for when it is re-exported or renamed.
+    crate_path: syn::Path,
+}
+
+impl syn::parse::Parse for AutoUpcastArgs {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self, Error> {
+        let crate_path = if input.is_empty() {
+            syn::parse_quote!(::powerset_enum)
+        } else {
+            parse_crate_path_arg(input)?
+        };
+        if !input.is_empty() {
+            return Err(input.error("unknown auto_upcast argument"));
+        }
+        Ok(AutoUpcastArgs { crate_path })
+    }
+}
+
+/// Parses `crate = "path::to::powerset_enum"`.
+pub fn parse_crate_path_arg(input: syn::parse::ParseStream) -> Result<syn::Path, Error> {
+    input.parse::<syn::token::Crate>()?;
+    input.parse::<syn::token::Eq>()?;
+    input.parse::<syn::LitStr>()?.parse()
+}
+
+pub fn auto_upcast_impl(args: AutoUpcastArgs, mut input: syn::ItemFn) -> Result<TokenStream, Error> {
+    // Without a return type there is no error for `?` to convert to.
+    if let syn::ReturnType::Type(_, return_type) = &input.decl.output {
+        let return_type = (**return_type).clone();
+        rewrite_try_expressions(&mut input.block, &args.crate_path, return_type);
+    }
+    Ok(quote!(#input))
+}
+
+/// Rewrites the `?` expressions of a function's body, so that errors that are subsets of a
+/// powerset `enum` are upcast into the function's error, and other errors go through `From` as
+/// usual.
+///
+/// The error is upcast only when it can be upcast to the error of `return_type`, which must be a
+/// `Result`, so subsets of other `enum`s - and subsets converted into non-subset errors - go
+/// through `From`.
+///
+/// Closures, `async` blocks and nested items have their own `?` target, so they are left as is.
+pub fn rewrite_try_expressions(block: &mut syn::Block, crate_path: &syn::Path, mut return_type: syn::Type) {
+    // `impl Trait` cannot be named in the body, and the `Ok` type it stands for does not affect the
+    // error anyway.
+    ImplTraitEraser.visit_type_mut(&mut return_type);
+    let target = syn::parse_quote!(<#return_type as #crate_path::ResultError>::Error);
+    TryRewriter { crate_path, target }.visit_block_mut(block);
+}
+
+struct ImplTraitEraser;
+
+impl VisitMut for ImplTraitEraser {
+    fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+        if let syn::Type::ImplTrait(_) = ty {
+            *ty = syn::parse_quote!(());
+        } else {
+            syn::visit_mut::visit_type_mut(self, ty);
+        }
+    }
+}
+
+struct TryRewriter<'a> {
+    crate_path: &'a syn::Path,
+    /// The function's error type.
+    target: syn::Type,
+}
+
+impl VisitMut for TryRewriter<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        syn::visit_mut::visit_expr_mut(self, expr);
+        if let syn::Expr::Try(syn::ExprTry { attrs, expr: operand, .. }) = expr {
+            let crate_path = self.crate_path;
+            let target = &self.target;
+            // Parenthesized, so that it is not taken for a `match` statement when it starts one.
+            *expr = syn::parse_quote! {
+                #(#attrs)*
+                (match #operand {
+                    core::result::Result::Ok(value) => value,
+                    core::result::Result::Err(error) => return core::result::Result::Err({
+                        #[allow(unused_imports)]
+                        use #crate_path::{ViaFrom as _, ViaUpcast as _};
+                        (&#crate_path::TryUpcast::<_, #target>::new(&error)).kind().convert(error)
+                    }),
+                })
+            };
+        }
+    }
+
+    fn visit_expr_closure_mut(&mut self, _: &mut syn::ExprClosure) {}
+
+    fn visit_expr_async_mut(&mut self, _: &mut syn::ExprAsync) {}
+
+    fn visit_expr_try_block_mut(&mut self, _: &mut syn::ExprTryBlock) {}
+
+    fn visit_item_mut(&mut self, _: &mut syn::Item) {}
+}
@@ -3,8 +3,11 @@ extern crate proc_macro;
 
 use syn::parse_macro_input;
 
+mod auto_upcast_impl;
+mod match_powerset_impl;
 mod powerset_enum_impl;
 mod powerset_macro_impl;
+mod throws_impl;
 
 #[proc_macro]
 pub fn powerset(args: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -14,6 +17,16 @@ pub fn powerset(args: proc_macro::TokenStream) -> proc_macro::TokenStream {
     }
 }
 
+/// The implementation of `powerset_enum::match_powerset!`, which passes it the path of the crate.
+#[doc(hidden)]
+#[proc_macro]
+pub fn match_powerset(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    match match_powerset_impl::match_powerset_impl(parse_macro_input!(input)) {
+        Ok(output) => output.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
 /// Parametrize an `enum` to make it a powerset (set of all subsets), and create a macro with the
 /// same name of the `enum` for easy notation of the subsets.
 ///
@@ -21,10 +34,43 @@ pub fn powerset(args: proc_macro::TokenStream) -> proc_macro::TokenStream {
 /// ```
 #[proc_macro_attribute]
 pub fn powerset_enum(
-    _args: proc_macro::TokenStream,
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    match powerset_enum_impl::powerset_enum_impl(parse_macro_input!(args), parse_macro_input!(input)) {
+        Ok(output) => output.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// Make a function return a subset of a powerset `enum` as its error, and wrap its tail
+/// expression in `Ok`.
+///
+/// `#[throws(Error: A, B)] fn foo() -> T` is `fn foo() -> Result<T, Error![A, B]>`. Like with
+/// [`#[auto_upcast]`](attr.auto_upcast.html), subsets of the `enum` are upcast by `?`.
+///
+/// See [powerset_enum](../powerset_enum/index.html) for more info.
+#[proc_macro_attribute]
+pub fn throws(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    match throws_impl::throws_impl(parse_macro_input!(args), parse_macro_input!(input)) {
+        Ok(output) => output.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// Make `?` in a function upcast errors that are subsets of a powerset `enum` into the function's
+/// error. Other errors are converted with `From` as usual.
+///
+/// See [powerset_enum](../powerset_enum/index.html) for more info.
+#[proc_macro_attribute]
+pub fn auto_upcast(
+    args: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    match powerset_enum_impl::powerset_enum_impl(parse_macro_input!(input)) {
+    match auto_upcast_impl::auto_upcast_impl(parse_macro_input!(args), parse_macro_input!(input)) {
         Ok(output) => output.into(),
         Err(error) => error.to_compile_error().into(),
     }
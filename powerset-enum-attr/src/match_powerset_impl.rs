@@ -0,0 +1,147 @@
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+
+use quote::quote;
+use syn::parse::Error;
+
+/// The input of `match_powerset!`, after the `macro_rules!` wrapper prepended the crate path:
+/// `$crate; scrutinee { arms... }`.
+#[derive(Debug)]
+pub struct MatchPowersetInput {
+    crate_path: syn::Path,
+    scrutinee: syn::Expr,
+    arms: Vec<MatchPowersetArm>,
+}
+
+#[derive(Debug)]
+struct MatchPowersetArm {
+    /// The variant's type and the pattern for it - from `Type(...)`, `Type { ... }` or `Type`,
+    /// which destructure the variant's payload or tag, or from `binding: Type`, for payloads that
+    /// cannot be destructured by a pattern named after them. `None` for `_`, which takes all the
+    /// variants that were not handled by the previous arms.
+    variant: Option<(syn::Type, syn::Pat)>,
+    body: syn::Expr,
+}
+
+impl syn::parse::Parse for MatchPowersetInput {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self, Error> {
+        let crate_path = syn::Path::parse_mod_style(input)?;
+        input.parse::<syn::token::Semi>()?;
+
+        // The scrutinee is followed by a block, which would be parsed as a struct literal with it.
+        let mut scrutinee = Vec::new();
+        while !input.is_empty() {
+            scrutinee.push(input.parse::<TokenTree>()?);
+        }
+        let arms_group = match scrutinee.pop() {
+            Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Brace && !scrutinee.is_empty() => {
+                group.clone()
+            }
+            _ => {
+                return Err(input.error("expected `match_powerset!(value { arms... })`"));
+            }
+        };
+        let scrutinee = syn::parse2(scrutinee.into_iter().collect())?;
+
+        let arms = syn::parse::Parser::parse2(
+            |input: syn::parse::ParseStream| {
+                let mut arms = Vec::new();
+                while !input.is_empty() {
+                    arms.push(input.parse()?);
+                }
+                Ok(arms)
+            },
+            arms_group.stream(),
+        )?;
+
+        Ok(MatchPowersetInput { crate_path, scrutinee, arms })
+    }
+}
+
+impl syn::parse::Parse for MatchPowersetArm {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self, Error> {
+        let variant = if input.peek(syn::token::Underscore) && input.peek2(syn::token::FatArrow) {
+            input.parse::<syn::token::Underscore>()?;
+            None
+        } else if (input.peek(syn::Ident) || input.peek(syn::token::Underscore))
+            && input.peek2(syn::token::Colon)
+            && !input.peek2(syn::token::Colon2)
+        {
+            let pat = input.parse()?;
+            input.parse::<syn::token::Colon>()?;
+            Some((input.parse()?, pat))
+        } else {
+            let pat: syn::Pat = input.parse()?;
+            let path = match &pat {
+                syn::Pat::TupleStruct(pat) => pat.path.clone(),
+                syn::Pat::Struct(pat) => pat.path.clone(),
+                syn::Pat::Path(syn::PatPath { qself: None, path }) => path.clone(),
+                syn::Pat::Ident(syn::PatIdent { by_ref: None, mutability: None, ident, subpat: None }) => {
+                    ident.clone().into()
+                }
+                _ => {
+                    return Err(Error::new_spanned(
+                        pat,
+                        "expected a pattern named after a variant's type, `binding: Type` or `_`",
+                    ));
+                }
+            };
+            Some((syn::Type::Path(syn::TypePath { qself: None, path }), pat))
+        };
+        if input.peek(syn::token::Or) {
+            return Err(input.error("match_powerset! arms cannot have alternatives - use an arm for each variant"));
+        }
+        if input.peek(syn::token::If) {
+            return Err(input.error("match_powerset! arms cannot have guards"));
+        }
+        input.parse::<syn::token::FatArrow>()?;
+        let body: syn::Expr = input.parse()?;
+        let requires_comma = !matches!(
+            body,
+            syn::Expr::Block(_)
+                | syn::Expr::Unsafe(_)
+                | syn::Expr::If(_)
+                | syn::Expr::Match(_)
+                | syn::Expr::Loop(_)
+                | syn::Expr::While(_)
+                | syn::Expr::ForLoop(_)
+        );
+        if (requires_comma && !input.is_empty()) || input.peek(syn::token::Comma) {
+            input.parse::<syn::token::Comma>()?;
+        }
+        Ok(MatchPowersetArm { variant, body })
+    }
+}
+
+pub fn match_powerset_impl(input: MatchPowersetInput) -> Result<TokenStream, Error> {
+    let MatchPowersetInput { crate_path, scrutinee, arms } = input;
+    let rest = quote!(__match_powerset_rest);
+
+    if arms.is_empty() {
+        return Ok(quote!(<_ as #crate_path::Empty>::absurd(#scrutinee)));
+    }
+
+    // Built from the last arm up, each arm's `match` going in the `Ok` branch of the previous one.
+    let mut expansion = quote!(<_ as #crate_path::Handled>::absurd(#rest));
+    for (i, arm) in arms.iter().enumerate().rev() {
+        let MatchPowersetArm { variant, body } = arm;
+        let value = if i == 0 { quote!(#scrutinee) } else { rest.clone() };
+        expansion = match variant {
+            Some((variant, pat)) => quote! {
+                match <_ as #crate_path::WithoutVariant<#variant>>::remove_possibility(#value) {
+                    core::result::Result::Err(#pat) => #body,
+                    core::result::Result::Ok(#rest) => #expansion,
+                }
+            },
+            None => {
+                if i + 1 != arms.len() {
+                    return Err(Error::new_spanned(body, "`_` must be the last arm of match_powerset!"));
+                }
+                quote!({
+                    let _ = #value;
+                    #body
+                })
+            }
+        };
+    }
+    Ok(expansion)
+}
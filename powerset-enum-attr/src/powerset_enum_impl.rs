@@ -1,205 +1,2051 @@
 use proc_macro2::TokenStream;
 
-use quote::quote;
+use quote::{quote, ToTokens};
 use syn::parse::Error;
 
-pub fn powerset_enum_impl(mut input: syn::ItemEnum) -> Result<TokenStream, Error> {
-    if !input.generics.params.is_empty() {
-        return Err(Error::new_spanned(
-            input.generics,
-            "powerset-enum does not support generics",
-        ));
+/// The arguments of the `#[powerset_enum(...)]` attribute.
+#[derive(Debug)]
+pub struct PowersetEnumArgs {
+    /// The path of the powerset-enum crate, for when it is re-exported or renamed.
+    crate_path: syn::Path,
+    /// Generate `Display` and `std::error::Error` implementations.
+    error: bool,
+    /// Generate `Serialize` and `Deserialize` implementations.
+    serde: bool,
+    /// Generate axum's `IntoResponse` implementations.
+    axum: bool,
+    /// The name of the module for the types generated for the variants, instead of the default.
+    module: Option<syn::Ident>,
+    /// `$crate`, followed by the path of the `enum`'s module when it is not in the crate root -
+    /// set when the macro for the subsets is exported.
+    exported_module: Option<TokenStream>,
+}
+
+impl Default for PowersetEnumArgs {
+    fn default() -> Self {
+        PowersetEnumArgs {
+            crate_path: syn::parse_quote!(::powerset_enum),
+            error: false,
+            serde: false,
+            axum: false,
+            module: None,
+            exported_module: None,
+        }
+    }
+}
+
+impl syn::parse::Parse for PowersetEnumArgs {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self, Error> {
+        let mut args = PowersetEnumArgs::default();
+        let metas = syn::punctuated::Punctuated::<syn::NestedMeta, syn::token::Comma>::parse_terminated(input)?;
+        for meta in metas {
+            match meta {
+                syn::NestedMeta::Meta(syn::Meta::Word(ref word)) if word == "error" => {
+                    args.error = true;
+                }
+                syn::NestedMeta::Meta(syn::Meta::Word(ref word)) if word == "serde" => {
+                    if !cfg!(feature = "serde") {
+                        return Err(Error::new_spanned(
+                            word,
+                            "`#[powerset_enum(serde)]` requires the `serde` feature of powerset-enum",
+                        ));
+                    }
+                    args.serde = true;
+                }
+                syn::NestedMeta::Meta(syn::Meta::Word(ref word)) if word == "axum" => {
+                    if !cfg!(feature = "axum") {
+                        return Err(Error::new_spanned(
+                            word,
+                            "`#[powerset_enum(axum)]` requires the `axum` feature of powerset-enum",
+                        ));
+                    }
+                    args.axum = true;
+                }
+                syn::NestedMeta::Meta(syn::Meta::Word(ref word)) if word == "export" => {
+                    args.exported_module = Some(quote!($crate));
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    ref ident,
+                    lit: syn::Lit::Str(ref module_path),
+                    ..
+                })) if ident == "export" => {
+                    let module_path: syn::Path = module_path.parse()?;
+                    args.exported_module = Some(quote!($crate::#module_path));
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    ref ident,
+                    lit: syn::Lit::Str(ref crate_path),
+                    ..
+                })) if ident == "crate" => {
+                    args.crate_path = crate_path.parse()?;
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    ref ident,
+                    lit: syn::Lit::Str(ref module),
+                    ..
+                })) if ident == "module" => {
+                    args.module = Some(module.parse()?);
+                }
+                meta => {
+                    return Err(Error::new_spanned(meta, "unknown powerset_enum argument"));
+                }
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// The arguments of the `#[powerset(...)]` attributes on a variant.
+#[derive(Debug, Default)]
+struct VariantArgs {
+    /// The variant's code - an integer or a string literal.
+    code: Option<syn::Lit>,
+    /// The variant's HTTP status.
+    status: Option<u16>,
+    /// The status the process exits with when `main` fails with the variant.
+    exit_code: Option<u8>,
+}
+
+impl VariantArgs {
+    /// Parses the `#[powerset(...)]` attributes of a variant, and removes them from it.
+    fn take_from(variant: &mut syn::Variant) -> Result<Self, Error> {
+        let mut args = VariantArgs::default();
+        let (powerset_attrs, other_attrs): (Vec<_>, Vec<_>) =
+            variant.attrs.drain(..).partition(|attr| attr.path.is_ident("powerset"));
+        variant.attrs = other_attrs;
+        for attr in powerset_attrs {
+            let metas = match attr.parse_meta()? {
+                syn::Meta::List(list) => list.nested,
+                meta => {
+                    return Err(Error::new_spanned(meta, "expected `#[powerset(...)]`"));
+                }
+            };
+            for meta in metas {
+                match meta {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                        ref ident,
+                        ref lit,
+                        ..
+                    })) if ident == "code" => {
+                        match lit {
+                            syn::Lit::Int(_) | syn::Lit::Str(_) => {}
+                            _ => {
+                                return Err(Error::new_spanned(lit, "codes must be integers or strings"));
+                            }
+                        }
+                        if args.code.is_some() {
+                            return Err(Error::new_spanned(ident, "the variant already has a code"));
+                        }
+                        args.code = Some(lit.clone());
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                        ref ident,
+                        ref lit,
+                        ..
+                    })) if ident == "status" => {
+                        if !cfg!(feature = "http") {
+                            return Err(Error::new_spanned(
+                                ident,
+                                "`#[powerset(status = ...)]` requires the `http` feature of powerset-enum",
+                            ));
+                        }
+                        let status = match lit {
+                            syn::Lit::Int(status) if (100..1000).contains(&status.value()) => status.value() as u16,
+                            _ => {
+                                return Err(Error::new_spanned(lit, "statuses must be integers from 100 to 999"));
+                            }
+                        };
+                        if args.status.is_some() {
+                            return Err(Error::new_spanned(ident, "the variant already has a status"));
+                        }
+                        args.status = Some(status);
+                    }
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                        ref ident,
+                        ref lit,
+                        ..
+                    })) if ident == "exit_code" => {
+                        let exit_code = match lit {
+                            syn::Lit::Int(exit_code) if (1..256).contains(&exit_code.value()) => exit_code.value() as u8,
+                            _ => {
+                                return Err(Error::new_spanned(lit, "exit codes must be integers from 1 to 255"));
+                            }
+                        };
+                        if args.exit_code.is_some() {
+                            return Err(Error::new_spanned(ident, "the variant already has an exit code"));
+                        }
+                        args.exit_code = Some(exit_code);
+                    }
+                    meta => {
+                        return Err(Error::new_spanned(meta, "unknown powerset argument"));
+                    }
+                }
+            }
+        }
+        Ok(args)
+    }
+}
+
+pub fn powerset_enum_impl(args: PowersetEnumArgs, mut input: syn::ItemEnum) -> Result<TokenStream, Error> {
+    let user_generics = input.generics.clone();
+    let PowersetEnumArgs {crate_path, exported_module, ..} = &args;
+    let generic_prefixes = GenericPrefixes::avoiding(&quote!(#input #crate_path #exported_module));
+
+    let mut replaced_variants = Vec::new();
+    let mut fixed_variants = Vec::new();
+    let mut used_type_params = Vec::new();
+    let has_user_facing_types = has_user_facing_types(&input);
+    let payloads_module_ident = match &args.module {
+        Some(module) => module.clone(),
+        None if has_user_facing_types => make_payloads_module_ident(&input.ident),
+        None => make_hidden_payloads_module_ident(&input.ident),
+    };
+    // Items in a module cannot name the items of a function body, so the module is left out when
+    // nothing needs it - letting `enum`s in function bodies hold the function's types.
+    let tags_module_ident = if args.module.is_some() || has_user_facing_types || args.exported_module.is_some() {
+        Some(&payloads_module_ident)
+    } else {
+        None
+    };
+    let mut generated_payloads = Vec::new();
+    let mut generated_tags = Vec::new();
+    let mut variant_args = Vec::new();
+    let derive_attrs: Vec<_> = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("derive"))
+        .cloned()
+        .collect();
+    let derived_traits = derived_trait_paths(&derive_attrs)?;
+    let tag_method_param = syn::Ident::new(&generic_prefixes.subset, proc_macro2::Span::call_site());
+    if args.serde {
+        // The generated types still derive them, but the `enum` gets implementations that
+        // understand the subsets instead.
+        input.attrs = without_serde_derives(&input.attrs)?;
+    }
+
+    for variant in input.variants.iter_mut() {
+        if variant.discriminant.is_some() {
+            return Err(Error::new_spanned(
+                variant,
+                "powerset-enum variants cannot have discriminants",
+            ));
+        }
+        variant_args.push(VariantArgs::take_from(variant)?);
+        if let Some(generated_payload) = gen_payload_type(
+            variant,
+            &payloads_module_ident,
+            &user_generics,
+            &derive_attrs,
+            &args,
+        ) {
+            generated_payloads.push(generated_payload);
+        }
+        let field = if let syn::Fields::Unnamed(fields) = &mut variant.fields {
+            fields.unnamed.iter_mut().next().expect("gen_payload_type leaves a single item")
+        } else {
+            unreachable!("gen_payload_type leaves a single unnamed item")
+        };
+
+        // A variant that holds one of the enum's own type parameters is already parametrized, and
+        // its payload type could be anything - including the other variants' payload types - so
+        // it cannot get a slot of its own.
+        if let Some(type_param) = type_param_of(&field.ty, &user_generics) {
+            used_type_params.push(type_param.clone());
+            fixed_variants.push(FixedVariant {
+                variant_ident: variant.ident.clone(),
+                type_param: type_param.clone(),
+            });
+            continue;
+        }
+
+        let idx = replaced_variants.len();
+        let generic_ident = make_generic_ident(&generic_prefixes.slot, idx);
+        let original_type =
+            std::mem::replace(&mut field.ty, make_generic_type(generic_ident.clone()));
+
+        let GeneratedTag {
+            payload_alias,
+            definition: tag_definition,
+            tag,
+            tag_path,
+            tag_generics,
+        } = gen_tag_type(
+            &input.ident,
+            &variant.ident,
+            &original_type,
+            tags_module_ident,
+            &user_generics,
+            &derived_traits,
+            &tag_method_param,
+        );
+        generated_payloads.push(payload_alias);
+        generated_tags.push(tag_definition);
+
+        replaced_variants.push(ReplacedVariant {
+            idx,
+            ty: original_type,
+            tag,
+            tag_path,
+            tag_generics,
+            variant_ident: variant.ident.clone(),
+            addressable_by_type: true,
+        });
+
+        input
+            .generics
+            .params
+            .push(syn::GenericParam::Type(generic_ident.into()));
+    }
+
+    let phantom_variant = make_phantom_variant(&user_generics, &used_type_params, &args.crate_path);
+    let has_phantom_variant = phantom_variant.is_some();
+    input.variants.extend(phantom_variant);
+
+    // Variants that share their payload type cannot be addressed by it - only by their tags.
+    let payload_type_names: Vec<String> = replaced_variants
+        .iter()
+        .map(|v| v.ty.clone().into_token_stream().to_string())
+        .collect();
+    for (replaced_variant, payload_type_name) in replaced_variants.iter_mut().zip(&payload_type_names) {
+        replaced_variant.addressable_by_type = payload_type_names
+            .iter()
+            .filter(|name| *name == payload_type_name)
+            .count()
+            == 1;
+    }
+
+    let powerset_enum = PowersetEnum {
+        ident: input.ident.clone(),
+        payloads_module_ident: payloads_module_ident.clone(),
+        generics: user_generics,
+        replaced_variants,
+        fixed_variants,
+        has_phantom_variant,
+        variant_idents: input
+            .variants
+            .iter()
+            .map(|variant| variant.ident.clone())
+            .filter(|ident| ident != "__PowersetEnumPhantom")
+            .collect(),
+        variant_args,
+        crate_path: args.crate_path.clone(),
+        generic_prefixes,
+    };
+
+    let reexport = if args.exported_module.is_some() {
+        let crate_path = &args.crate_path;
+        quote! {
+            #[doc(hidden)]
+            pub use #crate_path as __powerset_enum;
+        }
+    } else {
+        quote!()
+    };
+
+    let payloads_module = if tags_module_ident.is_none() {
+        // The tags are defined next to the `enum` instead.
+        quote!(#(#generated_tags)*)
+    } else {
+        let vis = &input.vis;
+        let doc = if args.module.is_some() || has_user_facing_types {
+            let doc = format!("Types generated for the variants of [{}].", input.ident);
+            quote!(#[doc = #doc])
+        } else {
+            quote!(#[doc(hidden)])
+        };
+        let tags_doc = format!(
+            "Tags for addressing the variants of [{}] by name instead of by payload type.",
+            input.ident
+        );
+        quote! {
+            #doc
+            #vis mod #payloads_module_ident {
+                #[allow(unused_imports)]
+                use super::*;
+
+                #reexport
+
+                #(#generated_payloads)*
+
+                #[doc = #tags_doc]
+                pub mod tags {
+                    #[allow(unused_imports)]
+                    use super::super::*;
+
+                    #(#generated_tags)*
+                }
+            }
+        }
+    };
+
+    let variant_trait_impls = gen_with_variant_trait_impls(&powerset_enum)?;
+    let error_from_trait_impls = gen_error_from_trait_impls(&powerset_enum)?;
+    let never_variant_trait_impls = gen_never_with_variant_trait_impl(&powerset_enum)?;
+    let without_trait_impls = gen_without_trait_impls(&powerset_enum)?;
+    let upcast_slot_trait_impls = gen_upcast_slot_trait_impls(&powerset_enum)?;
+    let methods_on_enum_impl = gen_methods_on_enum_impl(&powerset_enum)?;
+    let narrow_slot_trait_impls = gen_narrow_slot_trait_impls(&powerset_enum)?;
+    let narrow_trait_impl = gen_narrow_trait_impl(&powerset_enum)?;
+    let slot_set_operations_trait_impls = gen_slot_set_operations_trait_impls(&powerset_enum)?;
+    let set_operations_trait_impl = gen_set_operations_trait_impl(&powerset_enum)?;
+    let empty_trait_impl = gen_empty_trait_impl(&powerset_enum)?;
+    let slot_presence_trait_impls = gen_slot_presence_trait_impls(&powerset_enum)?;
+    let slot_payload_trait_impls = gen_slot_payload_trait_impls(&powerset_enum)?;
+    let handled_trait_impls = gen_handled_trait_impls(&powerset_enum)?;
+    let introspection_impl = gen_introspection_impl(&powerset_enum)?;
+    let powerset_trait_impl = gen_powerset_trait_impl(&powerset_enum)?;
+    let dyn_error_methods_impl = gen_dyn_error_methods_impl(&powerset_enum)?;
+    let codes_impl = gen_codes_impl(&powerset_enum)?;
+    let status_impl = gen_status_impl(&powerset_enum, &args)?;
+    let exit_code_impl = gen_exit_code_impl(&powerset_enum)?;
+    let powerset_macro = gen_powerset_macro(&powerset_enum, &args)?;
+    let error_trait_impls = if args.error {
+        gen_error_trait_impls(&powerset_enum)?
+    } else {
+        quote!()
+    };
+    let serde_trait_impls = if args.serde {
+        gen_serde_trait_impls(&powerset_enum)?
+    } else {
+        quote!()
+    };
+
+    Ok(quote! {
+        #input
+        #payloads_module
+        #variant_trait_impls
+        #error_from_trait_impls
+        #never_variant_trait_impls
+        #without_trait_impls
+        #upcast_slot_trait_impls
+        #methods_on_enum_impl
+        #narrow_slot_trait_impls
+        #narrow_trait_impl
+        #slot_set_operations_trait_impls
+        #set_operations_trait_impl
+        #empty_trait_impl
+        #slot_presence_trait_impls
+        #slot_payload_trait_impls
+        #handled_trait_impls
+        #introspection_impl
+        #powerset_trait_impl
+        #dyn_error_methods_impl
+        #codes_impl
+        #status_impl
+        #exit_code_impl
+        #powerset_macro
+        #error_trait_impls
+        #serde_trait_impls
+    })
+}
+
+/// Removes `Serialize` and `Deserialize` from the `derive` attributes.
+fn without_serde_derives(attrs: &[syn::Attribute]) -> Result<Vec<syn::Attribute>, Error> {
+    let mut result = Vec::new();
+    for attr in attrs {
+        if !attr.path.is_ident("derive") {
+            result.push(attr.clone());
+            continue;
+        }
+        let derived: Vec<_> = derived_trait_paths(std::slice::from_ref(attr))?
+            .into_iter()
+            .filter(|path| {
+                let ident = &path.segments.last().expect("paths are not empty").value().ident;
+                ident != "Serialize" && ident != "Deserialize"
+            })
+            .collect();
+        if !derived.is_empty() {
+            let mut attr = attr.clone();
+            attr.tts = quote!((#(#derived),*));
+            result.push(attr);
+        }
+    }
+    Ok(result)
+}
+
+/// The paths of the traits listed in `derive` attributes.
+fn derived_trait_paths(derive_attrs: &[syn::Attribute]) -> Result<Vec<syn::Path>, Error> {
+    let mut paths = Vec::new();
+    for attr in derive_attrs {
+        let derived = syn::parse::Parser::parse2(
+            |input: syn::parse::ParseStream| {
+                let content;
+                syn::parenthesized!(content in input);
+                syn::punctuated::Punctuated::<syn::Path, syn::token::Comma>::parse_terminated(&content)
+            },
+            attr.tts.clone(),
+        )?;
+        paths.extend(derived);
+    }
+    Ok(paths)
+}
+
+#[derive(Debug)]
+struct ReplacedVariant {
+    idx: usize,
+    ty: syn::Type,
+    tag: syn::Type,
+    /// The tag without its generic arguments, for constructing it.
+    tag_path: syn::Path,
+    /// The subset of the `enum`'s generics used by the payload type, which the tag is generic over.
+    tag_generics: syn::Generics,
+    variant_ident: syn::Ident,
+    /// `false` when other variants have the same payload type.
+    addressable_by_type: bool,
+}
+
+impl ReplacedVariant {
+    /// The types by which the variant can be addressed in `WithVariant`, `WithoutVariant` and
+    /// `From`, along with whether or not that type is the tag.
+    fn keys(&self) -> impl Iterator<Item = (&syn::Type, bool)> {
+        let payload_key = if self.addressable_by_type {
+            Some((&self.ty, false))
+        } else {
+            None
+        };
+        payload_key.into_iter().chain(Some((&self.tag, true)))
+    }
+}
+
+#[derive(Debug)]
+struct PowersetEnum {
+    ident: syn::Ident,
+    /// The module that holds the types generated for the variants.
+    payloads_module_ident: syn::Ident,
+    /// The generics the user declared on the `enum`, before the slot parameters were added.
+    generics: syn::Generics,
+    replaced_variants: Vec<ReplacedVariant>,
+    /// Variants whose payload is one of the `enum`'s own type parameters.
+    fixed_variants: Vec<FixedVariant>,
+    /// Whether the uninhabited `__PowersetEnumPhantom` variant was added.
+    has_phantom_variant: bool,
+    /// The names of all the variants, in the order they were declared.
+    variant_idents: Vec<syn::Ident>,
+    /// The arguments of the `#[powerset(...)]` attributes of the variants, in the same order.
+    variant_args: Vec<VariantArgs>,
+    crate_path: syn::Path,
+    generic_prefixes: GenericPrefixes,
+}
+
+/// Prefixes for the names of the generated generic parameters.
+#[derive(Debug)]
+struct GenericPrefixes {
+    /// For the parameters of the `enum` itself.
+    slot: String,
+    /// For the source subset in conversions.
+    source: String,
+    /// For the target subset in conversions.
+    target: String,
+    /// For the target subset in conversions, when given as a single type.
+    subset: String,
+}
+
+impl GenericPrefixes {
+    /// Generated parameters are named by a prefix followed by an index. Each prefix is lengthened
+    /// with underscores until no identifier in `tokens` can be mistaken for such a parameter, so
+    /// that they don't shadow the user's types.
+    fn avoiding(tokens: &TokenStream) -> Self {
+        let mut idents = Vec::new();
+        collect_idents_and_lifetimes(tokens.clone().into_iter(), &mut idents, &mut Vec::new());
+        let idents: Vec<String> = idents.iter().map(|ident| ident.to_string()).collect();
+        let name_avoiding_idents = |base: &str| {
+            let mut name = base.to_owned();
+            while idents.contains(&name) {
+                name.push('_');
+            }
+            name
+        };
+        let prefix_avoiding_idents = |base: &str| {
+            let mut prefix = base.to_owned();
+            while idents.iter().any(|ident| {
+                ident.len() > prefix.len()
+                    && ident.starts_with(&prefix)
+                    && ident[prefix.len()..].chars().all(|c| c.is_ascii_digit())
+            }) {
+                prefix.push('_');
+            }
+            prefix
+        };
+        GenericPrefixes {
+            slot: prefix_avoiding_idents("T"),
+            source: prefix_avoiding_idents("O"),
+            target: prefix_avoiding_idents("N"),
+            subset: name_avoiding_idents("Target"),
+        }
+    }
+}
+
+/// A variant whose payload type is one of the `enum`'s own type parameters.
+#[derive(Debug)]
+struct FixedVariant {
+    variant_ident: syn::Ident,
+    type_param: syn::Ident,
+}
+
+impl PowersetEnum {
+    /// The user's generic parameters (without defaults) followed by the given slot parameters.
+    fn impl_generics(&self, slot_params: impl IntoIterator<Item = syn::Ident>) -> TokenStream {
+        let user_params = self.generics.params.iter().map(|param| {
+            let mut param = param.clone();
+            match &mut param {
+                syn::GenericParam::Type(param) => {
+                    param.eq_token = None;
+                    param.default = None;
+                }
+                syn::GenericParam::Const(param) => {
+                    param.eq_token = None;
+                    param.default = None;
+                }
+                syn::GenericParam::Lifetime(_) => {}
+            }
+            param
+        });
+        let slot_params = slot_params.into_iter();
+        quote!(<#(#user_params,)* #(#slot_params),*>)
+    }
+
+    /// The `enum` type, parametrized by the user's generic parameters and the given slot types.
+    fn ty<T: quote::ToTokens>(&self, slot_types: impl IntoIterator<Item = T>) -> TokenStream {
+        let ident = &self.ident;
+        let user_args = self.generics.params.iter().map(|param| match param {
+            syn::GenericParam::Type(param) => {
+                let ident = &param.ident;
+                quote!(#ident)
+            }
+            syn::GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                quote!(#lifetime)
+            }
+            syn::GenericParam::Const(param) => {
+                let ident = &param.ident;
+                quote!(#ident)
+            }
+        });
+        let slot_types = slot_types.into_iter();
+        quote!(#ident<#(#user_args,)* #(#slot_types),*>)
+    }
+
+    fn where_clause(&self) -> Option<&syn::WhereClause> {
+        self.generics.where_clause.as_ref()
+    }
+
+    /// The slot parameters for implementing on every subset, the generics of such an impl and the
+    /// `enum` type parametrized by them.
+    fn any_subset(&self) -> (Vec<syn::Ident>, TokenStream, TokenStream) {
+        let num_slots = self.replaced_variants.len();
+        let slot_idents: Vec<_> = make_generic_idents(&self.generic_prefixes.slot, 0..num_slots).collect();
+        let impl_generics = self.impl_generics(slot_idents.iter().cloned());
+        let enum_type = self.ty(&slot_idents);
+        (slot_idents, impl_generics, enum_type)
+    }
+
+    /// Bounds for telling whether each slot holds its variant's payload, through the `SlotPresence`
+    /// of its tag.
+    fn presence_bounds<'a>(&'a self, slot_idents: &'a [syn::Ident]) -> impl Iterator<Item = syn::WherePredicate> + 'a {
+        let crate_path = &self.crate_path;
+        self.replaced_variants.iter().map(move |v| {
+            let tag = &v.tag;
+            let slot = &slot_idents[v.idx];
+            syn::parse_quote!(#tag: #crate_path::SlotPresence<#slot>)
+        })
+    }
+
+    /// The position of the variant in the `enum`'s declaration.
+    fn variant_position(&self, variant_ident: &syn::Ident) -> usize {
+        self.variant_idents
+            .iter()
+            .position(|ident| ident == variant_ident)
+            .expect("every variant is listed")
+    }
+
+    /// A `match` on `self` - a reference to the `enum` - with an arm for each variant. It must
+    /// cover the phantom variant even though it is uninhabited, and a reference to an `enum`
+    /// without variants is dereferenced, since references are always considered inhabited.
+    fn ref_match(&self, arm: impl Fn(&syn::Ident) -> TokenStream) -> TokenStream {
+        let enum_ident = &self.ident;
+        let variant_idents = self
+            .replaced_variants
+            .iter()
+            .map(|v| &v.variant_ident)
+            .chain(self.fixed_variants.iter().map(|v| &v.variant_ident));
+        let mut arms: Vec<_> = variant_idents
+            .map(|variant_ident| {
+                let result = arm(variant_ident);
+                quote! {
+                    #enum_ident::#variant_ident(value) => #result
+                }
+            })
+            .collect();
+        if self.has_phantom_variant {
+            arms.push(quote! {
+                #enum_ident::__PowersetEnumPhantom(.., never) => match *never {}
+            });
+        }
+        if arms.is_empty() {
+            quote!(match *self {})
+        } else {
+            quote! {
+                match self {
+                    #(#arms),*
+                }
+            }
+        }
+    }
+
+    /// Match arms that rebuild the fixed variants unchanged in the target type.
+    fn fixed_variants_passthrough_arms(&self, wrap: impl Fn(TokenStream) -> TokenStream) -> Vec<TokenStream> {
+        let enum_ident = &self.ident;
+        self.fixed_variants
+            .iter()
+            .map(|FixedVariant { variant_ident, .. }| {
+                let result = wrap(quote!(#enum_ident::#variant_ident(value)));
+                quote! {
+                    #enum_ident::#variant_ident(value) => #result
+                }
+            })
+            .collect()
+    }
+}
+
+/// Whether the `enum` has generated types that users need - payload types generated for variants
+/// that are not single items, or tags for variants that share their payload type. Otherwise the
+/// module for the variants is only generated when the subset macro is exported, and gets a name
+/// that does not take the `enum`'s name in snake case from sibling modules.
+fn has_user_facing_types(input: &syn::ItemEnum) -> bool {
+    let mut payload_type_names = Vec::new();
+    for variant in &input.variants {
+        match &variant.fields {
+            syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let name = fields.unnamed[0].ty.clone().into_token_stream().to_string();
+                if payload_type_names.contains(&name) {
+                    return true;
+                }
+                payload_type_names.push(name);
+            }
+            _ => return true,
+        }
+    }
+    false
+}
+
+/// The name of the module for the generated code when users don't need it.
+fn make_hidden_payloads_module_ident(enum_ident: &syn::Ident) -> syn::Ident {
+    let module_ident = make_payloads_module_ident(enum_ident);
+    syn::Ident::new(&format!("__powerset_enum_{}", module_ident), enum_ident.span())
+}
+
+/// The name of the module that holds the payload types generated for the variants - the name of
+/// the `enum` in snake case.
+fn make_payloads_module_ident(enum_ident: &syn::Ident) -> syn::Ident {
+    let enum_name = enum_ident.to_string();
+    let chars: Vec<char> = enum_name.chars().collect();
+    let mut module_name = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let after_lowercase = i > 0 && !chars[i - 1].is_uppercase() && chars[i - 1] != '_';
+            let acronym_end = i > 0
+                && chars[i - 1].is_uppercase()
+                && chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if after_lowercase || acronym_end {
+                module_name.push('_');
+            }
+            module_name.extend(c.to_lowercase());
+        } else {
+            module_name.push(c);
+        }
+    }
+    syn::Ident::new(&module_name, enum_ident.span())
+}
+
+/// Unit variants, variants with named items and variants with multiple unnamed items get a struct
+/// generated for them in the payloads module, and are changed to hold that struct as their single
+/// unnamed item.
+fn gen_payload_type(
+    variant: &mut syn::Variant,
+    payloads_module_ident: &syn::Ident,
+    generics: &syn::Generics,
+    derive_attrs: &[syn::Attribute],
+    args: &PowersetEnumArgs,
+) -> Option<TokenStream> {
+    let payload_ident = &variant.ident;
+    let doc_attrs = variant.attrs.iter().filter(|attr| attr.path.is_ident("doc"));
+    let mut payload_generics = syn::Generics::default();
+    let (payload, payload_type) = match &variant.fields {
+        syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => return None,
+        syn::Fields::Unit => (
+            quote! {
+                #(#doc_attrs)*
+                #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+                pub struct #payload_ident;
+            },
+            quote!(#payloads_module_ident::#payload_ident),
+        ),
+        fields => {
+            payload_generics = generics_used_by(fields, generics);
+            let (_, type_generics, where_clause) = payload_generics.split_for_impl();
+            let fields = fields.iter().map(|field| syn::Field {
+                vis: syn::parse_quote!(pub),
+                ..field.clone()
+            });
+            let definition = if let syn::Fields::Named(_) = variant.fields {
+                quote! {
+                    pub struct #payload_ident #payload_generics #where_clause {
+                        #(#fields),*
+                    }
+                }
+            } else {
+                quote! {
+                    pub struct #payload_ident #payload_generics (#(#fields),*) #where_clause;
+                }
+            };
+            (
+                quote! {
+                    #(#doc_attrs)*
+                    #(#derive_attrs)*
+                    #definition
+                },
+                quote!(#payloads_module_ident::#payload_ident #type_generics),
+            )
+        }
+    };
+    let payload = if args.serde && variant.fields == syn::Fields::Unit {
+        // The markers don't get the `enum`'s derives, so they are implemented here.
+        let crate_path = &args.crate_path;
+        let serde = quote!(#crate_path::serde_support::serde);
+        let name = payload_ident.to_string();
+        quote! {
+            #payload
+
+            impl #serde::Serialize for #payload_ident {
+                fn serialize<S: #serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_unit_struct(#name)
+                }
+            }
+
+            impl<'de> #serde::Deserialize<'de> for #payload_ident {
+                fn deserialize<D: #serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    #crate_path::serde_support::deserialize_unit(deserializer, #name)
+                }
+            }
+        }
+    } else {
+        payload
+    };
+    variant.fields = syn::Fields::Unnamed(syn::parse_quote!((#payload_type)));
+    if !args.error {
+        return Some(payload);
+    }
+    // Generated payload types have no message of their own, so they are displayed like they are
+    // debug-formatted.
+    let (impl_generics, type_generics, _) = payload_generics.split_for_impl();
+    let where_clause = with_predicates(
+        payload_generics.where_clause.as_ref(),
+        Some(syn::parse_quote!(Self: core::fmt::Debug)),
+    );
+    Some(quote! {
+        #payload
+
+        impl #impl_generics core::fmt::Display for #payload_ident #type_generics #where_clause {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                core::fmt::Debug::fmt(self, f)
+            }
+        }
+
+        impl #impl_generics std::error::Error for #payload_ident #type_generics #where_clause {}
+    })
+}
+
+/// A `payload_type: trait_path` bound. It is higher-ranked, so that a payload type that does not
+/// implement the trait leaves the impl out instead of failing on a bound that does not depend on
+/// any parameter.
+fn payload_bound(payload_type: impl ToTokens, trait_path: impl ToTokens) -> syn::WherePredicate {
+    syn::parse_quote!(for<'__powerset_enum> #payload_type: #trait_path)
+}
+
+/// `where_clause` with `predicates` added to it.
+fn with_predicates(
+    where_clause: Option<&syn::WhereClause>,
+    predicates: impl IntoIterator<Item = syn::WherePredicate>,
+) -> syn::WhereClause {
+    let mut where_clause = where_clause.cloned().unwrap_or_else(|| syn::parse_quote!(where));
+    where_clause.predicates.extend(predicates);
+    where_clause
+}
+
+/// A tag is a newtype around the payload, named after the variant, that addresses it even when
+/// other variants have the same payload type. Tags are defined in the `tags` module when it is
+/// given, and next to the `enum` under a hidden name otherwise.
+fn gen_tag_type(
+    enum_ident: &syn::Ident,
+    variant_ident: &syn::Ident,
+    payload_type: &syn::Type,
+    tags_module_ident: Option<&syn::Ident>,
+    generics: &syn::Generics,
+    derived_traits: &[syn::Path],
+    method_param: &syn::Ident,
+) -> GeneratedTag {
+    let tag_generics = generics_used_by(payload_type, generics);
+    let (_, type_generics, where_clause) = tag_generics.split_for_impl();
+    let doc = format!("Tag for the `{}` variant.", variant_ident);
+    let (tag_ident, tag_path, payload_alias, payload): (_, syn::Path, _, _) = if let Some(tags_module_ident) = tags_module_ident {
+        // The tag has the same name as the variant, which is often also the name of the payload
+        // type, so the payload type is referred to through an alias defined outside the tags
+        // module.
+        let payload_alias_ident = make_payload_alias_ident(variant_ident);
+        // Type aliases don't enforce bounds, so they are stripped to avoid a warning.
+        let alias_params = tag_generics.params.iter().map(|param| match param {
+            syn::GenericParam::Type(param) => {
+                let ident = &param.ident;
+                quote!(#ident)
+            }
+            syn::GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                quote!(#lifetime)
+            }
+            syn::GenericParam::Const(param) => {
+                let ident = &param.ident;
+                let ty = &param.ty;
+                quote!(const #ident: #ty)
+            }
+        });
+        let payload_alias = quote! {
+            #[doc(hidden)]
+            pub type #payload_alias_ident<#(#alias_params),*> = #payload_type;
+        };
+        (
+            variant_ident.clone(),
+            syn::parse_quote!(#tags_module_ident::tags::#variant_ident),
+            payload_alias,
+            quote!(super::#payload_alias_ident #type_generics),
+        )
+    } else {
+        let tag_ident = syn::Ident::new(&format!("__{}Tag{}", enum_ident, variant_ident), variant_ident.span());
+        (tag_ident.clone(), tag_ident.into(), quote!(), quote!(#payload_type))
+    };
+    let trait_impls = gen_tag_trait_impls(
+        &tag_ident,
+        variant_ident,
+        &payload,
+        &tag_generics,
+        derived_traits,
+        method_param,
+    );
+    let definition = quote! {
+        #[doc = #doc]
+        pub struct #tag_ident #tag_generics (pub #payload) #where_clause;
+
+        #(#trait_impls)*
+    };
+    let tag = syn::parse_quote!(#tag_path #type_generics);
+    GeneratedTag {
+        payload_alias,
+        definition,
+        tag,
+        tag_path,
+        tag_generics,
+    }
+}
+
+/// Implementations of the standard derivable traits that the `enum` derives, for a tag. Deriving
+/// them would require the payload to implement them even when the `enum` - whose derives are only
+/// bounded on its parameters - is never used with a subset that holds it, so they are bounded on
+/// the payload instead. Other derives are not applied to tags.
+fn gen_tag_trait_impls(
+    tag_ident: &syn::Ident,
+    variant_ident: &syn::Ident,
+    payload_type: &TokenStream,
+    tag_generics: &syn::Generics,
+    derived_traits: &[syn::Path],
+    method_param: &syn::Ident,
+) -> Vec<TokenStream> {
+    let (impl_generics, type_generics, where_clause) = tag_generics.split_for_impl();
+    let tag_name = variant_ident.to_string();
+    derived_traits.iter().filter_map(|path| {
+        let ident = &path.segments.last().expect("paths are not empty").value().ident;
+        let (trait_path, body) = match ident.to_string().as_str() {
+            "Debug" => (quote!(core::fmt::Debug), quote! {
+                fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    f.debug_tuple(#tag_name).field(&self.0).finish()
+                }
+            }),
+            "Clone" => (quote!(core::clone::Clone), quote! {
+                fn clone(&self) -> Self {
+                    #tag_ident(core::clone::Clone::clone(&self.0))
+                }
+            }),
+            "Copy" => (quote!(core::marker::Copy), quote!()),
+            "PartialEq" => (quote!(core::cmp::PartialEq), quote! {
+                fn eq(&self, other: &Self) -> bool {
+                    core::cmp::PartialEq::eq(&self.0, &other.0)
+                }
+            }),
+            "Eq" => (quote!(core::cmp::Eq), quote!()),
+            "PartialOrd" => (quote!(core::cmp::PartialOrd), quote! {
+                fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+                    core::cmp::PartialOrd::partial_cmp(&self.0, &other.0)
+                }
+            }),
+            "Ord" => (quote!(core::cmp::Ord), quote! {
+                fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+                    core::cmp::Ord::cmp(&self.0, &other.0)
+                }
+            }),
+            "Hash" => (quote!(core::hash::Hash), quote! {
+                fn hash<#method_param: core::hash::Hasher>(&self, state: &mut #method_param) {
+                    core::hash::Hash::hash(&self.0, state)
+                }
+            }),
+            "Default" => (quote!(core::default::Default), quote! {
+                fn default() -> Self {
+                    #tag_ident(core::default::Default::default())
+                }
+            }),
+            _ => return None,
+        };
+        let where_clause = with_predicates(where_clause, Some(payload_bound(payload_type, &trait_path)));
+        Some(quote! {
+            impl #impl_generics #trait_path for #tag_ident #type_generics #where_clause {
+                #body
+            }
+        })
+    }).collect()
+}
+
+struct GeneratedTag {
+    payload_alias: TokenStream,
+    definition: TokenStream,
+    tag: syn::Type,
+    tag_path: syn::Path,
+    tag_generics: syn::Generics,
+}
+
+/// The subset of `generics` that is mentioned by `tokens`.
+fn generics_used_by(tokens: &impl quote::ToTokens, generics: &syn::Generics) -> syn::Generics {
+    let mut used_idents = Vec::new();
+    let mut used_lifetimes = Vec::new();
+    collect_idents_and_lifetimes(
+        quote!(#tokens).into_iter(),
+        &mut used_idents,
+        &mut used_lifetimes,
+    );
+    let is_used = |param: &syn::GenericParam| match param {
+        syn::GenericParam::Type(param) => used_idents.contains(&param.ident),
+        syn::GenericParam::Const(param) => used_idents.contains(&param.ident),
+        syn::GenericParam::Lifetime(param) => used_lifetimes.contains(&param.lifetime.ident),
+    };
+    let params: syn::punctuated::Punctuated<_, _> =
+        generics.params.iter().filter(|param| is_used(param)).cloned().collect();
+    let unused_idents: Vec<_> = generics
+        .params
+        .iter()
+        .filter(|param| !is_used(param))
+        .filter_map(|param| match param {
+            syn::GenericParam::Type(param) => Some(param.ident.clone()),
+            syn::GenericParam::Const(param) => Some(param.ident.clone()),
+            syn::GenericParam::Lifetime(_) => None,
+        })
+        .collect();
+    let where_clause = generics.where_clause.as_ref().map(|where_clause| {
+        let mut where_clause = where_clause.clone();
+        where_clause.predicates = where_clause
+            .predicates
+            .into_iter()
+            .filter(|predicate| {
+                let mut predicate_idents = Vec::new();
+                collect_idents_and_lifetimes(
+                    quote!(#predicate).into_iter(),
+                    &mut predicate_idents,
+                    &mut Vec::new(),
+                );
+                !predicate_idents.iter().any(|ident| unused_idents.contains(ident))
+            })
+            .collect();
+        where_clause
+    });
+    syn::Generics {
+        lt_token: Some(Default::default()),
+        params,
+        gt_token: Some(Default::default()),
+        where_clause,
+    }
+}
+
+fn collect_idents_and_lifetimes(
+    tokens: impl Iterator<Item = proc_macro2::TokenTree>,
+    idents: &mut Vec<syn::Ident>,
+    lifetimes: &mut Vec<syn::Ident>,
+) {
+    let mut after_apostrophe = false;
+    for token in tokens {
+        match token {
+            proc_macro2::TokenTree::Group(group) => {
+                collect_idents_and_lifetimes(group.stream().into_iter(), idents, lifetimes);
+            }
+            proc_macro2::TokenTree::Ident(ident) => {
+                if after_apostrophe {
+                    lifetimes.push(ident);
+                } else {
+                    idents.push(ident);
+                }
+            }
+            proc_macro2::TokenTree::Punct(ref punct) if punct.as_char() == '\'' => {
+                after_apostrophe = true;
+                continue;
+            }
+            _ => {}
+        }
+        after_apostrophe = false;
+    }
+}
+
+fn type_param_of<'a>(ty: &'a syn::Type, generics: &syn::Generics) -> Option<&'a syn::Ident> {
+    if let syn::Type::Path(syn::TypePath { qself: None, path }) = ty {
+        if let Some(ident) = path_as_ident(path) {
+            if generics.type_params().any(|param| param.ident == *ident) {
+                return Some(ident);
+            }
+        }
+    }
+    None
+}
+
+/// Once the payload types are replaced with the slot parameters, the `enum`'s own lifetimes and
+/// type parameters may no longer be used by any variant. This creates an uninhabited variant that
+/// uses them, so that the `enum` still compiles. Being uninhabited, it never needs a `match` arm.
+fn make_phantom_variant(
+    generics: &syn::Generics,
+    used_type_params: &[syn::Ident],
+    crate_path: &syn::Path,
+) -> Option<syn::Variant> {
+    let lifetimes = generics.lifetimes().map(|param| &param.lifetime);
+    let type_params = generics
+        .type_params()
+        .map(|param| &param.ident)
+        .filter(|ident| !used_type_params.contains(ident));
+    let phantom_fields: Vec<syn::Type> = lifetimes
+        .map(|lifetime| syn::parse_quote!(core::marker::PhantomData<&#lifetime ()>))
+        .chain(type_params.map(|ident| syn::parse_quote!(core::marker::PhantomData<#ident>)))
+        .collect();
+    if phantom_fields.is_empty() {
+        return None;
+    }
+    let never = make_never(crate_path);
+    Some(syn::parse_quote! {
+        #[doc(hidden)]
+        #[allow(dead_code)]
+        __PowersetEnumPhantom(#(#phantom_fields,)* #never)
+    })
+}
+
+fn path_as_ident(path: &syn::Path) -> Option<&syn::Ident> {
+    if path.leading_colon.is_some() || path.segments.len() != 1 {
+        return None;
+    }
+    let segment = path.segments.first()?.into_value();
+    if let syn::PathArguments::None = segment.arguments {
+        Some(&segment.ident)
+    } else {
+        None
+    }
+}
+
+fn make_generic_ident(prefix: &str, idx: usize) -> syn::Ident {
+    syn::Ident::new(
+        &format!("{}{}", prefix, idx),
+        proc_macro2::Span::call_site(),
+    )
+}
+
+fn make_generic_idents(
+    prefix: &str,
+    rng: std::ops::Range<usize>,
+) -> impl Iterator<Item = syn::Ident> {
+    let prefix = prefix.to_owned();
+    rng.map(move |i| make_generic_ident(&prefix, i))
+}
+
+fn make_generic_type(ident: syn::Ident) -> syn::Type {
+    syn::Type::Path(syn::TypePath {
+        qself: None,
+        path: ident.into(),
+    })
+}
+
+fn make_payload_alias_ident(variant_ident: &syn::Ident) -> syn::Ident {
+    syn::Ident::new(
+        &format!("__{}Payload", variant_ident),
+        proc_macro2::Span::call_site(),
+    )
+}
+
+fn make_never(crate_path: &syn::Path) -> syn::Type {
+    syn::parse_quote!(#crate_path::Never)
+}
+
+fn gen_never_with_variant_trait_impl(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let num_slots = powerset_enum.replaced_variants.len();
+    let impl_generics = powerset_enum.impl_generics(make_generic_idents(&powerset_enum.generic_prefixes.slot, 0..num_slots));
+    let enum_type = powerset_enum.ty(make_generic_idents(&powerset_enum.generic_prefixes.slot, 0..num_slots));
+    let where_clause = powerset_enum.where_clause();
+    let never = make_never(crate_path);
+    Ok(quote! {
+        impl #impl_generics #crate_path::WithVariant<#never> for #enum_type #where_clause {
+            type With = Self;
+            fn add_possibility(self) -> Self::With {
+                self
+            }
+        }
+    })
+}
+
+fn gen_methods_on_enum_impl(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let enum_ident = &powerset_enum.ident;
+    let num_slots = powerset_enum.replaced_variants.len();
+    let impl_generics = powerset_enum.impl_generics(make_generic_idents(&powerset_enum.generic_prefixes.source, 0..num_slots));
+    let source_type = powerset_enum.ty(make_generic_idents(&powerset_enum.generic_prefixes.source, 0..num_slots));
+    let final_target_generics = make_generic_idents(&powerset_enum.generic_prefixes.target, 0..num_slots);
+    let final_target_generics = quote!(#(#final_target_generics),*);
+    let final_target_type = powerset_enum.ty(make_generic_idents(&powerset_enum.generic_prefixes.target, 0..num_slots));
+    let where_clause = powerset_enum.where_clause();
+
+    let upcast_slot_traits: Vec<_> = powerset_enum.replaced_variants.iter().map(|v| {
+        let source = make_generic_ident(&powerset_enum.generic_prefixes.source, v.idx);
+        let target = make_generic_ident(&powerset_enum.generic_prefixes.target, v.idx);
+        quote!(#crate_path::UpcastSlot<#source, #target>)
+    }).collect();
+
+    let where_bounds = powerset_enum.replaced_variants.iter().zip(&upcast_slot_traits).map(|(v, upcast_slot_trait)| {
+        let tag = &v.tag;
+        quote!(#tag: #upcast_slot_trait)
+    });
+
+    let subset = syn::Ident::new(&powerset_enum.generic_prefixes.subset, proc_macro2::Span::call_site());
+
+    let upcast_match_arms = powerset_enum.replaced_variants.iter().zip(&upcast_slot_traits).map(|(v, upcast_slot_trait)| {
+        let ReplacedVariant {tag, variant_ident, ..} = v;
+        quote! {
+            #enum_ident::#variant_ident(value) => #enum_ident::#variant_ident(<#tag as #upcast_slot_trait>::upcast_slot(value))
+        }
+    }).chain(powerset_enum.fixed_variants_passthrough_arms(|value| value));
+
+    let upcast_impl_generics = powerset_enum.impl_generics(
+        make_generic_idents(&powerset_enum.generic_prefixes.source, 0..num_slots)
+            .chain(make_generic_idents(&powerset_enum.generic_prefixes.target, 0..num_slots)),
+    );
+    let upcast_where_clause = with_predicates(
+        where_clause,
+        where_bounds.map(|bound| syn::parse_quote!(#bound)),
+    );
+
+    Ok(quote! {
+        impl #upcast_impl_generics #crate_path::Upcast<#final_target_type> for #source_type #upcast_where_clause {
+            fn upcast(self) -> #final_target_type {
+                match self {
+                    #(#upcast_match_arms),*
+                }
+            }
+        }
+
+        impl #impl_generics #source_type #where_clause {
+            pub fn upcast<#final_target_generics>(self) -> #final_target_type
+            where Self: #crate_path::Upcast<#final_target_type>
+            {
+                #crate_path::Upcast::upcast(self)
+            }
+
+            pub fn try_narrow<#subset>(self) -> Result<#subset, <Self as #crate_path::Narrow<#subset>>::Rest>
+            where Self: #crate_path::Narrow<#subset>
+            {
+                #crate_path::Narrow::try_narrow(self)
+            }
+        }
+    })
+}
+
+fn gen_narrow_trait_impl(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let enum_ident = &powerset_enum.ident;
+    let num_slots = powerset_enum.replaced_variants.len();
+    let prefixes = &powerset_enum.generic_prefixes;
+    let impl_generics = powerset_enum.impl_generics(
+        make_generic_idents(&prefixes.source, 0..num_slots)
+            .chain(make_generic_idents(&prefixes.target, 0..num_slots)),
+    );
+    let source_type = powerset_enum.ty(make_generic_idents(&prefixes.source, 0..num_slots));
+    let target_type = powerset_enum.ty(make_generic_idents(&prefixes.target, 0..num_slots));
+
+    let narrow_slots: Vec<_> = powerset_enum.replaced_variants.iter().map(|v| {
+        let tag = &v.tag;
+        let source = make_generic_ident(&prefixes.source, v.idx);
+        let target = make_generic_ident(&prefixes.target, v.idx);
+        (tag, quote!(#crate_path::NarrowSlot<#source, #target>))
+    }).collect();
+
+    let where_clause = with_predicates(
+        powerset_enum.where_clause(),
+        narrow_slots.iter().map(|(tag, narrow_slot_trait)| syn::parse_quote!(#tag: #narrow_slot_trait)),
+    );
+    let rest_type = powerset_enum.ty(narrow_slots.iter().map(|(tag, narrow_slot_trait)| {
+        quote!(<#tag as #narrow_slot_trait>::Rest)
+    }));
+
+    let match_arms = powerset_enum.replaced_variants.iter().zip(&narrow_slots).map(|(v, (tag, narrow_slot_trait))| {
+        let variant_ident = &v.variant_ident;
+        quote! {
+            #enum_ident::#variant_ident(value) => match <#tag as #narrow_slot_trait>::narrow_slot(value) {
+                Ok(value) => Ok(#enum_ident::#variant_ident(value)),
+                Err(value) => Err(#enum_ident::#variant_ident(value)),
+            }
+        }
+    }).chain(powerset_enum.fixed_variants_passthrough_arms(|value| quote!(Ok(#value))));
+
+    let split_impl_generics = powerset_enum.impl_generics(make_generic_idents(&prefixes.source, 0..num_slots));
+    let split_where_clause = powerset_enum.where_clause();
+
+    Ok(quote! {
+        impl #split_impl_generics #crate_path::Split for #source_type #split_where_clause {}
+
+        impl #impl_generics #crate_path::Narrow<#target_type> for #source_type #where_clause {
+            type Rest = #rest_type;
+            fn try_narrow(self) -> Result<#target_type, Self::Rest> {
+                match self {
+                    #(#match_arms),*
+                }
+            }
+        }
+    })
+}
+
+/// The subset without any variants is uninhabited - as long as the variants with the `enum`'s own
+/// type parameters are uninhabited too.
+fn gen_empty_trait_impl(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let enum_ident = &powerset_enum.ident;
+    let never = make_never(crate_path);
+    let impl_generics = powerset_enum.impl_generics(None);
+    let empty_type = powerset_enum.ty(powerset_enum.replaced_variants.iter().map(|_| &never));
+    let where_clause = with_predicates(
+        powerset_enum.where_clause(),
+        powerset_enum.fixed_variants.iter().map(|FixedVariant {type_param, ..}| {
+            syn::parse_quote!(#type_param: #crate_path::Empty)
+        }),
+    );
+    let absurd_arms = powerset_enum.replaced_variants.iter().map(|ReplacedVariant {variant_ident, ..}| quote! {
+        #enum_ident::#variant_ident(value) => match value {}
+    }).chain(powerset_enum.fixed_variants.iter().map(|FixedVariant {variant_ident, type_param, ..}| quote! {
+        #enum_ident::#variant_ident(value) => <#type_param as #crate_path::Empty>::absurd(value)
+    }));
+    let phantom_arm = if powerset_enum.has_phantom_variant {
+        quote!(#enum_ident::__PowersetEnumPhantom(.., never) => match never {},)
+    } else {
+        quote!()
+    };
+    let absurd_target = syn::Ident::new(&powerset_enum.generic_prefixes.subset, proc_macro2::Span::call_site());
+    Ok(quote! {
+        impl #impl_generics #crate_path::Empty for #empty_type #where_clause {
+            fn absurd<#absurd_target>(self) -> #absurd_target {
+                match self {
+                    #(#absurd_arms,)*
+                    #phantom_arm
+                }
+            }
+        }
+    })
+}
+
+fn gen_slot_presence_trait_impls(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let never = make_never(crate_path);
+    let impls = powerset_enum.replaced_variants.iter().map(|replaced_variant| {
+        let ReplacedVariant {ty, tag, tag_generics, ..} = replaced_variant;
+        let (impl_generics, _, where_clause) = tag_generics.split_for_impl();
+        quote! {
+            impl #impl_generics #crate_path::SlotPresence<#ty> for #tag #where_clause {
+                const PRESENT: bool = true;
+            }
+
+            impl #impl_generics #crate_path::SlotPresence<#never> for #tag #where_clause {
+                const PRESENT: bool = false;
+            }
+        }
+    });
+    Ok(quote!(#( #impls )*))
+}
+
+fn gen_slot_payload_trait_impls(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let (_, impl_generics, enum_type) = powerset_enum.any_subset();
+    let where_clause = powerset_enum.where_clause();
+    let impls = powerset_enum.replaced_variants.iter().map(|ReplacedVariant {idx, ty, ..}| {
+        let idx = syn::LitInt::new(*idx as u64, syn::IntSuffix::None, proc_macro2::Span::call_site());
+        quote! {
+            impl #impl_generics #crate_path::SlotPayload<#idx> for #enum_type #where_clause {
+                type Payload = #ty;
+            }
+        }
+    });
+    Ok(quote!(#( #impls )*))
+}
+
+fn gen_handled_trait_impls(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let enum_ident = &powerset_enum.ident;
+    let never = make_never(crate_path);
+    let (slot_idents, impl_generics, enum_type) = powerset_enum.any_subset();
+    let absurd_target = syn::Ident::new(&powerset_enum.generic_prefixes.subset, proc_macro2::Span::call_site());
+
+    // Each slot is checked separately, so that the error names the payloads of the variants that
+    // were not handled rather than the whole subset.
+    let slot_handled_impls = powerset_enum.replaced_variants.iter().map(|ReplacedVariant {tag, tag_generics, ..}| {
+        let (impl_generics, _, where_clause) = tag_generics.split_for_impl();
+        quote! {
+            impl #impl_generics #crate_path::SlotHandled<#never> for #tag #where_clause {
+                fn absurd<#absurd_target>(slot: #never) -> #absurd_target {
+                    match slot {}
+                }
+            }
+        }
+    });
+    let where_clause = with_predicates(
+        powerset_enum.where_clause(),
+        powerset_enum.replaced_variants.iter().map(|v| -> syn::WherePredicate {
+            let tag = &v.tag;
+            let slot = &slot_idents[v.idx];
+            syn::parse_quote!(#tag: #crate_path::SlotHandled<#slot>)
+        }).chain(powerset_enum.fixed_variants.iter().map(|FixedVariant {type_param, ..}| {
+            syn::parse_quote!(#type_param: #crate_path::Empty)
+        })),
+    );
+    let absurd_arms = powerset_enum.replaced_variants.iter().map(|ReplacedVariant {idx, variant_ident, tag, ..}| {
+        let slot = &slot_idents[*idx];
+        quote! {
+            #enum_ident::#variant_ident(value) => <#tag as #crate_path::SlotHandled<#slot>>::absurd(value)
+        }
+    }).chain(powerset_enum.fixed_variants.iter().map(|FixedVariant {variant_ident, type_param, ..}| quote! {
+        #enum_ident::#variant_ident(value) => <#type_param as #crate_path::Empty>::absurd(value)
+    }));
+    let phantom_arm = if powerset_enum.has_phantom_variant {
+        quote!(#enum_ident::__PowersetEnumPhantom(.., never) => match never {},)
+    } else {
+        quote!()
+    };
+    Ok(quote! {
+        #(#slot_handled_impls)*
+
+        impl #impl_generics #crate_path::Handled for #enum_type #where_clause {
+            fn absurd<#absurd_target>(self) -> #absurd_target {
+                match self {
+                    #(#absurd_arms,)*
+                    #phantom_arm
+                }
+            }
+        }
+    })
+}
+
+fn gen_introspection_impl(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let (slot_idents, impl_generics, enum_type) = powerset_enum.any_subset();
+    let where_clause = powerset_enum.where_clause();
+
+    let payload_type_of = |variant_ident: &syn::Ident| -> TokenStream {
+        if let Some(v) = powerset_enum.replaced_variants.iter().find(|v| v.variant_ident == *variant_ident) {
+            let slot = &slot_idents[v.idx];
+            quote!(#slot)
+        } else {
+            let FixedVariant {type_param, ..} = powerset_enum.fixed_variants.iter()
+                .find(|v| v.variant_ident == *variant_ident)
+                .expect("every variant is either replaced or fixed");
+            quote!(#type_param)
+        }
+    };
+
+    let name_match = powerset_enum.ref_match(|variant_ident| {
+        let name = variant_ident.to_string();
+        quote!(#name)
+    });
+    let index_match = powerset_enum.ref_match(|variant_ident| {
+        let index = powerset_enum.variant_position(variant_ident);
+        quote!(#index)
+    });
+    let type_id_match = powerset_enum.ref_match(|variant_ident| {
+        let payload_type = payload_type_of(variant_ident);
+        quote!(core::any::TypeId::of::<#payload_type>())
+    });
+    let static_bounds = slot_idents.iter()
+        .chain(powerset_enum.fixed_variants.iter().map(|v| &v.type_param))
+        .map(|ident| quote!(#ident: 'static));
+
+    let presence_bounds = powerset_enum.presence_bounds(&slot_idents);
+    let names_where_clause = with_predicates(where_clause, presence_bounds);
+    let optional_names = powerset_enum.variant_idents.iter().map(|variant_ident| {
+        let name = variant_ident.to_string();
+        match powerset_enum.replaced_variants.iter().find(|v| v.variant_ident == *variant_ident) {
+            Some(ReplacedVariant {tag, idx, ..}) => {
+                let slot = &slot_idents[*idx];
+                quote! {
+                    if <#tag as #crate_path::SlotPresence<#slot>>::PRESENT { Some(#name) } else { None }
+                }
+            }
+            None => quote!(Some(#name)),
+        }
+    });
+    let num_variants = powerset_enum.variant_idents.len();
+
+    Ok(quote! {
+        impl #impl_generics #enum_type #where_clause {
+            /// The name of the variant.
+            pub fn variant_name(&self) -> &'static str {
+                #name_match
+            }
+
+            /// The position of the variant in the `enum`'s declaration.
+            pub fn variant_index(&self) -> usize {
+                #index_match
+            }
+
+            /// The `TypeId` of the variant's payload.
+            pub fn payload_type_id(&self) -> core::any::TypeId
+            where #(#static_bounds),*
+            {
+                #type_id_match
+            }
+        }
+
+        impl #impl_generics #enum_type #names_where_clause {
+            #[doc(hidden)]
+            const __POWERSET_ENUM_PRESENT_VARIANT_NAMES: ([&'static str; #num_variants], usize) =
+                #crate_path::present_values([#(#optional_names),*], "");
+
+            /// The names of the variants that are possible in this subset, in the order they were
+            /// declared.
+            pub const VARIANT_NAMES: &'static [&'static str] = Self::__POWERSET_ENUM_PRESENT_VARIANT_NAMES.0
+                .split_at(Self::__POWERSET_ENUM_PRESENT_VARIANT_NAMES.1).0;
+        }
+    })
+}
+
+fn gen_powerset_trait_impl(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let never = make_never(crate_path);
+    let (slot_idents, impl_generics, enum_type) = powerset_enum.any_subset();
+    let full_type = powerset_enum.ty(powerset_enum.replaced_variants.iter().map(|v| &v.ty));
+    let empty_type = powerset_enum.ty(powerset_enum.replaced_variants.iter().map(|_| &never));
+    // The same bounds as the `VARIANT_NAMES` it forwards.
+    let where_clause = with_predicates(
+        powerset_enum.where_clause(),
+        powerset_enum.presence_bounds(&slot_idents),
+    );
+    Ok(quote! {
+        impl #impl_generics #crate_path::Powerset for #enum_type #where_clause {
+            type Full = #full_type;
+            type Empty = #empty_type;
+            const VARIANT_COUNT: usize = Self::VARIANT_NAMES.len();
+            const VARIANT_NAMES: &'static [&'static str] = Self::VARIANT_NAMES;
+        }
+    })
+}
+
+fn gen_dyn_error_methods_impl(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let enum_ident = &powerset_enum.ident;
+    let (slot_idents, impl_generics, enum_type) = powerset_enum.any_subset();
+    let where_clause = powerset_enum.where_clause();
+    let dyn_error = syn::Ident::new(&powerset_enum.generic_prefixes.subset, proc_macro2::Span::call_site());
+
+    // In declaration order, so that the first of the variants that share a payload type wins.
+    let payloads: Vec<_> = powerset_enum.variant_idents.iter().map(|variant_ident| {
+        let payload_type = match powerset_enum.replaced_variants.iter().find(|v| v.variant_ident == *variant_ident) {
+            Some(v) => &slot_idents[v.idx],
+            None => &powerset_enum.fixed_variants.iter()
+                .find(|v| v.variant_ident == *variant_ident)
+                .expect("every variant is either replaced or fixed")
+                .type_param,
+        };
+        (variant_ident, payload_type)
+    }).collect();
+    let payload_bounds: Vec<_> = payloads.iter().map(|(_, payload_type)| {
+        quote!(#payload_type: std::error::Error + Send + Sync + 'static)
+    }).collect();
+    let payload_bounds = &payload_bounds;
+    let downcasts = payloads.iter().map(|(variant_ident, payload_type)| quote! {
+        let error = match #crate_path::DynError::downcast_payload::<#payload_type>(error) {
+            Ok(payload) => return Ok(#enum_ident::#variant_ident(payload)),
+            Err(error) => error,
+        };
+    });
+    let mut erase_arms: Vec<_> = payloads.iter().map(|(variant_ident, _)| quote! {
+        #enum_ident::#variant_ident(value) => #crate_path::DynError::from_payload(value)
+    }).collect();
+    if powerset_enum.has_phantom_variant {
+        erase_arms.push(quote! {
+            #enum_ident::__PowersetEnumPhantom(.., never) => match never {}
+        });
     }
 
-    let mut replaced_variants = Vec::new();
+    Ok(quote! {
+        impl #impl_generics #enum_type #where_clause {
+            /// Recover the subset from a type-erased error, by downcasting it to the payload type
+            /// of each variant in turn. The error is returned unchanged when none of them fit.
+            pub fn try_from_dyn<#dyn_error: #crate_path::DynError>(error: #dyn_error) -> Result<Self, #dyn_error>
+            where #(#payload_bounds),*
+            {
+                #(#downcasts)*
+                Err(error)
+            }
 
-    for (idx, variant) in input.variants.iter_mut().enumerate() {
-        if variant.discriminant.is_some() {
+            /// Erase the variant's payload into a type-erased error.
+            pub fn into_dyn<#dyn_error: #crate_path::DynError>(self) -> #dyn_error
+            where #(#payload_bounds),*
+            {
+                match self {
+                    #(#erase_arms),*
+                }
+            }
+
+            /// Erase the variant's payload into a `Box<dyn Error + Send + Sync>`.
+            pub fn into_boxed_dyn(self) -> Box<dyn std::error::Error + Send + Sync>
+            where #(#payload_bounds),*
+            {
+                self.into_dyn()
+            }
+        }
+    })
+}
+
+/// The codes given to the variants with `#[powerset(code = ...)]`, in declaration order, checked
+/// to be all of the same kind and distinct. `None` when no variant has a code.
+fn variant_codes(powerset_enum: &PowersetEnum) -> Result<Option<Vec<&syn::Lit>>, Error> {
+    let first_code = match powerset_enum.variant_args.iter().find_map(|args| args.code.as_ref()) {
+        Some(code) => code,
+        None => return Ok(None),
+    };
+    let mut codes = Vec::new();
+    for (variant_ident, args) in powerset_enum.variant_idents.iter().zip(&powerset_enum.variant_args) {
+        let code = match &args.code {
+            Some(code) => code,
+            None => {
+                return Err(Error::new_spanned(
+                    variant_ident,
+                    "every variant needs a `#[powerset(code = ...)]` when one of them has one",
+                ));
+            }
+        };
+        match (first_code, code) {
+            (syn::Lit::Int(_), syn::Lit::Int(code)) => {
+                if code.value() > u64::from(u32::MAX) {
+                    return Err(Error::new_spanned(code, "integer codes must fit in a u32"));
+                }
+            }
+            (syn::Lit::Str(_), syn::Lit::Str(_)) => {}
+            _ => {
+                return Err(Error::new_spanned(code, "the codes must be either all integers or all strings"));
+            }
+        }
+        let value = code_value(code);
+        if let Some(other) = codes.iter().position(|&other| code_value(other) == value) {
             return Err(Error::new_spanned(
-                variant,
-                "powerset-enum variants cannot have discriminants",
+                code,
+                format!("duplicate code - already used by `{}`", powerset_enum.variant_idents[other]),
             ));
         }
-        let field = match &mut variant.fields {
-            syn::Fields::Named(_) => None,
-            syn::Fields::Unit => None,
-            syn::Fields::Unnamed(fields) => {
-                if fields.unnamed.len() == 1 {
-                    fields.unnamed.iter_mut().next()
-                } else {
+        codes.push(code);
+    }
+    Ok(Some(codes))
+}
+
+fn code_value(code: &syn::Lit) -> String {
+    match code {
+        syn::Lit::Int(code) => code.value().to_string(),
+        syn::Lit::Str(code) => code.value(),
+        _ => unreachable!("codes are checked to be integers or strings"),
+    }
+}
+
+fn gen_codes_impl(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let codes = match variant_codes(powerset_enum)? {
+        Some(codes) => codes,
+        None => return Ok(quote!()),
+    };
+    let crate_path = &powerset_enum.crate_path;
+    let never = make_never(crate_path);
+    let enum_ident = &powerset_enum.ident;
+    let (slot_idents, impl_generics, enum_type) = powerset_enum.any_subset();
+    let where_clause = powerset_enum.where_clause();
+
+    let (code_type, code_param_type, filler) = if matches!(codes[0], syn::Lit::Int(_)) {
+        (quote!(u32), quote!(u32), quote!(0))
+    } else {
+        (quote!(&'static str), quote!(&str), quote!(""))
+    };
+    // Integer codes are emitted unsuffixed, so that they are typed by the signatures.
+    let codes: Vec<TokenStream> = codes.iter().map(|code| match code {
+        syn::Lit::Int(code) => proc_macro2::Literal::u32_unsuffixed(code.value() as u32).into_token_stream(),
+        code => code.into_token_stream(),
+    }).collect();
+    let code_of = |variant_ident: &syn::Ident| {
+        let index = powerset_enum.variant_position(variant_ident);
+        &codes[index]
+    };
+
+    let code_match = powerset_enum.ref_match(|variant_ident| {
+        let code = code_of(variant_ident);
+        quote!(#code)
+    });
+
+    let presence_bounds = powerset_enum.presence_bounds(&slot_idents);
+    let codes_where_clause = with_predicates(where_clause, presence_bounds);
+    let optional_codes = powerset_enum.variant_idents.iter().map(|variant_ident| {
+        let code = code_of(variant_ident);
+        match powerset_enum.replaced_variants.iter().find(|v| v.variant_ident == *variant_ident) {
+            Some(ReplacedVariant {tag, idx, ..}) => {
+                let slot = &slot_idents[*idx];
+                quote! {
+                    if <#tag as #crate_path::SlotPresence<#slot>>::PRESENT { Some(#code) } else { None }
+                }
+            }
+            None => quote!(Some(#code)),
+        }
+    });
+    let num_variants = powerset_enum.variant_idents.len();
+
+    let default_slot_impls = powerset_enum.replaced_variants.iter().map(|v| {
+        let ReplacedVariant {ty, tag, tag_generics, ..} = v;
+        let (impl_generics, _, tag_where_clause) = tag_generics.split_for_impl();
+        let default_where_clause = with_predicates(tag_where_clause, Some(payload_bound(ty, quote!(core::default::Default))));
+        quote! {
+            impl #impl_generics #crate_path::DefaultSlot<#ty> for #tag #default_where_clause {
+                fn default_slot() -> Option<#ty> {
+                    Some(core::default::Default::default())
+                }
+            }
+
+            impl #impl_generics #crate_path::DefaultSlot<#never> for #tag #tag_where_clause {
+                fn default_slot() -> Option<#never> {
                     None
                 }
             }
-        };
-        let field = if let Some(field) = field {
-            field
-        } else {
-            return Err(Error::new_spanned(
-                variant,
-                "powerset-enum variants must contain a single unnamed item",
-            ));
-        };
+        }
+    });
+    let default_bounds = powerset_enum.replaced_variants.iter().map(|v| {
+        let tag = &v.tag;
+        let slot = &slot_idents[v.idx];
+        quote!(#tag: #crate_path::DefaultSlot<#slot>)
+    }).chain(powerset_enum.fixed_variants.iter().map(|FixedVariant {type_param, ..}| {
+        quote!(#type_param: core::default::Default)
+    }));
+    let from_code_arms = powerset_enum.variant_idents.iter().map(|variant_ident| {
+        let code = code_of(variant_ident);
+        match powerset_enum.replaced_variants.iter().find(|v| v.variant_ident == *variant_ident) {
+            Some(ReplacedVariant {tag, idx, ..}) => {
+                let slot = &slot_idents[*idx];
+                quote! {
+                    #code => <#tag as #crate_path::DefaultSlot<#slot>>::default_slot().map(#enum_ident::#variant_ident)
+                }
+            }
+            None => quote! {
+                #code => Some(#enum_ident::#variant_ident(core::default::Default::default()))
+            },
+        }
+    });
 
-        let generic_ident = make_generic_ident("T", idx);
-        let original_type =
-            std::mem::replace(&mut field.ty, make_generic_type(generic_ident.clone()));
+    Ok(quote! {
+        #(#default_slot_impls)*
 
-        replaced_variants.push(ReplacedVariant {
-            idx,
-            ty: original_type,
-            variant_ident: variant.ident.clone(),
-        });
+        impl #impl_generics #enum_type #where_clause {
+            /// The code given to the variant with `#[powerset(code = ...)]`.
+            pub fn code(&self) -> #code_type {
+                #code_match
+            }
 
-        input
-            .generics
-            .params
-            .push(syn::GenericParam::Type(generic_ident.into()));
+            /// Build the variant with the given code from its payload's `Default`. Returns `None`
+            /// when no variant of the subset has that code.
+            pub fn from_code(code: #code_param_type) -> Option<Self>
+            where #(#default_bounds),*
+            {
+                match code {
+                    #(#from_code_arms,)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl #impl_generics #enum_type #codes_where_clause {
+            #[doc(hidden)]
+            const __POWERSET_ENUM_PRESENT_CODES: ([#code_type; #num_variants], usize) =
+                #crate_path::present_values([#(#optional_codes),*], #filler);
+
+            /// The codes of the variants that are possible in this subset, in the order they were
+            /// declared.
+            pub const CODES: &'static [#code_type] = Self::__POWERSET_ENUM_PRESENT_CODES.0
+                .split_at(Self::__POWERSET_ENUM_PRESENT_CODES.1).0;
+        }
+    })
+}
+
+fn gen_status_impl(powerset_enum: &PowersetEnum, args: &PowersetEnumArgs) -> Result<TokenStream, Error> {
+    if !args.axum && powerset_enum.variant_args.iter().all(|args| args.status.is_none()) {
+        return Ok(quote!());
     }
+    let crate_path = &powerset_enum.crate_path;
+    let http_support = quote!(#crate_path::http_support);
+    let (_, impl_generics, enum_type) = powerset_enum.any_subset();
+    let where_clause = powerset_enum.where_clause();
+
+    let status_match = powerset_enum.ref_match(|variant_ident| {
+        let index = powerset_enum.variant_position(variant_ident);
+        // Variants without a status are server errors.
+        let status = powerset_enum.variant_args[index].status.unwrap_or(500);
+        quote!(#http_support::status(#status))
+    });
 
-    let variant_trait_impls = gen_with_variant_trait_impls(&input.ident, &replaced_variants)?;
-    let error_from_trait_impls = gen_error_from_trait_impls(&input.ident, &replaced_variants)?;
-    let never_variant_trait_impls =
-        gen_never_with_variant_trait_impl(&input.ident, &replaced_variants)?;
-    let without_trait_impls = gen_without_trait_impls(&input.ident, &replaced_variants)?;
-    let methods_on_enum_impl = gen_methods_on_enum_impl(&input.ident, &replaced_variants)?;
-    let powerset_macro = gen_powerset_macro(&input.ident, &replaced_variants)?;
+    let into_response_impl = if args.axum {
+        let axum = quote!(#http_support::axum);
+        let display_where_clause = with_predicates(where_clause, Some(syn::parse_quote!(Self: core::fmt::Display)));
+        quote! {
+            impl #impl_generics #axum::response::IntoResponse for #enum_type #display_where_clause {
+                fn into_response(self) -> #axum::response::Response {
+                    let status = self.status();
+                    let body = #http_support::response_body(status, &self);
+                    #axum::response::IntoResponse::into_response((status, body))
+                }
+            }
+        }
+    } else {
+        quote!()
+    };
 
     Ok(quote! {
-        #input
-        #variant_trait_impls
-        #error_from_trait_impls
-        #never_variant_trait_impls
-        #without_trait_impls
-        #methods_on_enum_impl
-        #powerset_macro
+        impl #impl_generics #enum_type #where_clause {
+            /// The HTTP status given to the variant with `#[powerset(status = ...)]`, or
+            /// `500 Internal Server Error` if it was not given one.
+            pub fn status(&self) -> #http_support::http::StatusCode {
+                #status_match
+            }
+        }
+
+        #into_response_impl
     })
 }
 
-#[derive(Debug)]
-struct ReplacedVariant {
-    idx: usize,
-    ty: syn::Type,
-    variant_ident: syn::Ident,
-}
+fn gen_exit_code_impl(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    if powerset_enum.variant_args.iter().all(|args| args.exit_code.is_none()) {
+        return Ok(quote!());
+    }
+    let (_, impl_generics, enum_type) = powerset_enum.any_subset();
+    let where_clause = powerset_enum.where_clause();
 
-fn make_generic_ident(prefix: &str, idx: usize) -> syn::Ident {
-    syn::Ident::new(
-        &format!("{}{}", prefix, idx),
-        proc_macro2::Span::call_site(),
-    )
-}
+    let exit_code_match = powerset_enum.ref_match(|variant_ident| {
+        let index = powerset_enum.variant_position(variant_ident);
+        // Like a `main` that returns `Err`.
+        let exit_code = powerset_enum.variant_args[index].exit_code.unwrap_or(1);
+        quote!(#exit_code)
+    });
+    let display_where_clause = with_predicates(where_clause, Some(syn::parse_quote!(Self: core::fmt::Display)));
 
-fn make_generic_idents(
-    prefix: &'static str,
-    rng: std::ops::Range<usize>,
-) -> impl Iterator<Item = syn::Ident> {
-    rng.map(move |i| make_generic_ident(prefix, i))
-}
+    Ok(quote! {
+        impl #impl_generics #enum_type #where_clause {
+            /// The status given to the variant with `#[powerset(exit_code = ...)]`, or 1 if it was
+            /// not given one.
+            pub fn exit_code(&self) -> u8 {
+                #exit_code_match
+            }
+        }
 
-fn make_generic_type(ident: syn::Ident) -> syn::Type {
-    syn::Type::Path(syn::TypePath {
-        qself: None,
-        path: ident.into(),
+        impl #impl_generics std::process::Termination for #enum_type #display_where_clause {
+            fn report(self) -> std::process::ExitCode {
+                eprintln!("Error: {}", self);
+                std::process::ExitCode::from(self.exit_code())
+            }
+        }
     })
 }
 
-fn make_never() -> syn::Type {
-    syn::Type::Never(syn::TypeNever {
-        bang_token: Default::default(),
+fn gen_set_operations_trait_impl(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let num_slots = powerset_enum.replaced_variants.len();
+    let prefixes = &powerset_enum.generic_prefixes;
+    let impl_generics = powerset_enum.impl_generics(
+        make_generic_idents(&prefixes.source, 0..num_slots)
+            .chain(make_generic_idents(&prefixes.target, 0..num_slots)),
+    );
+    let self_type = powerset_enum.ty(make_generic_idents(&prefixes.source, 0..num_slots));
+    let other_type = powerset_enum.ty(make_generic_idents(&prefixes.target, 0..num_slots));
+
+    let slot_set_operations: Vec<_> = powerset_enum.replaced_variants.iter().map(|v| {
+        let tag = &v.tag;
+        let a = make_generic_ident(&prefixes.source, v.idx);
+        let b = make_generic_ident(&prefixes.target, v.idx);
+        (tag, quote!(#crate_path::SlotSetOperations<#a, #b>))
+    }).collect();
+
+    let where_clause = with_predicates(
+        powerset_enum.where_clause(),
+        slot_set_operations.iter().map(|(tag, slot_set_operations_trait)| syn::parse_quote!(#tag: #slot_set_operations_trait)),
+    );
+    let result_type = |operation: &str| {
+        let operation = syn::Ident::new(operation, proc_macro2::Span::call_site());
+        powerset_enum.ty(slot_set_operations.iter().map(move |(tag, slot_set_operations_trait)| {
+            quote!(<#tag as #slot_set_operations_trait>::#operation)
+        }))
+    };
+    let union_type = result_type("Union");
+    let intersection_type = result_type("Intersection");
+    let difference_type = result_type("Difference");
+
+    Ok(quote! {
+        impl #impl_generics #crate_path::SetOperations<#other_type> for #self_type #where_clause {
+            type Union = #union_type;
+            type Intersection = #intersection_type;
+            type Difference = #difference_type;
+        }
     })
 }
 
-fn gen_never_with_variant_trait_impl(
-    enum_ident: &syn::Ident,
-    replaced_variants: &[ReplacedVariant],
-) -> Result<TokenStream, Error> {
-    let impl_generics = make_generic_idents("T", 0..replaced_variants.len());
-    let type_generics = make_generic_idents("T", 0..replaced_variants.len());
-    Ok(quote! {
-        impl<#(#impl_generics),*> powerset_enum::WithVariant<!> for #enum_ident<#(#type_generics),*> {
-            type With = Self;
-            fn add_possibility(self) -> Self::With {
-                self
+fn gen_slot_set_operations_trait_impls(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let never = make_never(crate_path);
+    let impls = powerset_enum.replaced_variants.iter().map(|replaced_variant| {
+        let ReplacedVariant {ty, tag, tag_generics, ..} = replaced_variant;
+        let (impl_generics, _, where_clause) = tag_generics.split_for_impl();
+        quote! {
+            impl #impl_generics #crate_path::SlotSetOperations<#ty, #ty> for #tag #where_clause {
+                type Union = #ty;
+                type Intersection = #ty;
+                type Difference = #never;
+            }
+
+            impl #impl_generics #crate_path::SlotSetOperations<#ty, #never> for #tag #where_clause {
+                type Union = #ty;
+                type Intersection = #never;
+                type Difference = #ty;
+            }
+
+            impl #impl_generics #crate_path::SlotSetOperations<#never, #ty> for #tag #where_clause {
+                type Union = #ty;
+                type Intersection = #never;
+                type Difference = #never;
+            }
+
+            impl #impl_generics #crate_path::SlotSetOperations<#never, #never> for #tag #where_clause {
+                type Union = #never;
+                type Intersection = #never;
+                type Difference = #never;
             }
         }
-    })
+    });
+    Ok(quote!(#( #impls )*))
 }
 
-fn gen_methods_on_enum_impl(
-    enum_ident: &syn::Ident,
-    replaced_variants: &[ReplacedVariant],
-) -> Result<TokenStream, Error> {
-    let first_source_generics = make_generic_idents("O", 0..replaced_variants.len());
-    let first_source_generics = quote!(#(#first_source_generics),*);
-    let final_target_generics = make_generic_idents("N", 0..replaced_variants.len());
-    let final_target_generics = quote!(#(#final_target_generics),*);
+fn gen_narrow_slot_trait_impls(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let never = make_never(crate_path);
+    let impls = powerset_enum.replaced_variants.iter().map(|replaced_variant| {
+        let ReplacedVariant {ty, tag, tag_generics, ..} = replaced_variant;
+        let (impl_generics, _, where_clause) = tag_generics.split_for_impl();
+        quote! {
+            impl #impl_generics #crate_path::NarrowSlot<#ty, #ty> for #tag #where_clause {
+                type Rest = #never;
+                fn narrow_slot(source: #ty) -> Result<#ty, #never> {
+                    Ok(source)
+                }
+            }
 
-    let where_bounds = replaced_variants.iter().map(|v| {
-        let idx = v.idx;
-        let source_generics = make_generic_idents("N", 0..idx).chain(make_generic_idents("O", idx..replaced_variants.len()));
-        let target_generics = make_generic_idents("N", 0..idx+1).chain(make_generic_idents("O", idx+1..replaced_variants.len()));
-        let current_type = make_generic_ident("N", idx);
-        quote!{
-            #enum_ident<#(#source_generics),*> : powerset_enum::WithVariant<#current_type, With=#enum_ident<#(#target_generics),*>>
+            impl #impl_generics #crate_path::NarrowSlot<#ty, #never> for #tag #where_clause {
+                type Rest = #ty;
+                fn narrow_slot(source: #ty) -> Result<#never, #ty> {
+                    Err(source)
+                }
+            }
+
+            impl #impl_generics #crate_path::NarrowSlot<#never, #ty> for #tag #where_clause {
+                type Rest = #never;
+                fn narrow_slot(source: #never) -> Result<#ty, #never> {
+                    match source {}
+                }
+            }
+
+            impl #impl_generics #crate_path::NarrowSlot<#never, #never> for #tag #where_clause {
+                type Rest = #never;
+                fn narrow_slot(source: #never) -> Result<#never, #never> {
+                    match source {}
+                }
+            }
         }
     });
+    Ok(quote!(#( #impls )*))
+}
 
-    let add_possibility_statements = std::iter::repeat(quote! {
-        let result = powerset_enum::WithVariant::add_possibility(result);
-    })
-    .take(replaced_variants.len());
+fn gen_upcast_slot_trait_impls(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let never = make_never(crate_path);
+    let impls = powerset_enum.replaced_variants.iter().map(|replaced_variant| {
+        let ReplacedVariant {ty, tag, tag_generics, ..} = replaced_variant;
+        let (impl_generics, _, where_clause) = tag_generics.split_for_impl();
+        quote! {
+            impl #impl_generics #crate_path::UpcastSlot<#ty, #ty> for #tag #where_clause {
+                fn upcast_slot(source: #ty) -> #ty {
+                    source
+                }
+            }
 
-    Ok(quote! {
-        impl<#first_source_generics> #enum_ident<#first_source_generics> {
-            pub fn upcast<#final_target_generics>(self) -> #enum_ident<#final_target_generics>
-            where #(#where_bounds),*
-            {
-                let result = self;
-                #(#add_possibility_statements)*
-                result
+            impl #impl_generics #crate_path::UpcastSlot<#never, #ty> for #tag #where_clause {
+                fn upcast_slot(source: #never) -> #ty {
+                    match source {}
+                }
+            }
+
+            impl #impl_generics #crate_path::UpcastSlot<#never, #never> for #tag #where_clause {
+                fn upcast_slot(source: #never) -> #never {
+                    source
+                }
             }
         }
-    })
+    });
+    Ok(quote!(#( #impls )*))
 }
 
-fn gen_with_variant_trait_impls(
-    enum_ident: &syn::Ident,
-    replaced_variants: &[ReplacedVariant],
-) -> Result<TokenStream, Error> {
-    let impls = replaced_variants.iter().map(|replaced_variant| {
+fn gen_with_variant_trait_impls(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let enum_ident = &powerset_enum.ident;
+    let replaced_variants = &powerset_enum.replaced_variants;
+    let where_clause = powerset_enum.where_clause();
+    let impls = replaced_variants.iter().flat_map(|replaced_variant| replaced_variant.keys().map(move |(key, _)| {
         let ReplacedVariant {idx, ty, ..} = &replaced_variant;
-        let impl_generics = replaced_variants.iter().filter(|v| v.idx != *idx).map(|v| make_generic_ident("T", v.idx));
-        let impl_generics = quote!(#(#impl_generics),*);
-        let source_generic_params = replaced_variants.iter().map(|v| {
+        let impl_generics = powerset_enum.impl_generics(replaced_variants.iter().filter(|v| v.idx != *idx).map(|v| make_generic_ident(&powerset_enum.generic_prefixes.slot, v.idx)));
+        let source_type = powerset_enum.ty(replaced_variants.iter().map(|v| {
             if v.idx == *idx {
-                make_never()
+                make_never(crate_path)
             } else {
-                make_generic_type(make_generic_ident("T", v.idx))
+                make_generic_type(make_generic_ident(&powerset_enum.generic_prefixes.slot, v.idx))
             }
-        });
-        let type_exists_source_generic_params = replaced_variants.iter().map(|v| {
+        }));
+        let type_exists_source_type = powerset_enum.ty(replaced_variants.iter().map(|v| {
             if v.idx == *idx {
                 v.ty.clone()
             } else {
-                make_generic_type(make_generic_ident("T", v.idx))
+                make_generic_type(make_generic_ident(&powerset_enum.generic_prefixes.slot, v.idx))
             }
-        });
-        let target_generic_params = replaced_variants.iter().map(|v| {
+        }));
+        let target_type = powerset_enum.ty(replaced_variants.iter().map(|v| {
             if v.idx == *idx {
                 ty.clone()
             } else {
-                make_generic_type(make_generic_ident("T", v.idx))
+                make_generic_type(make_generic_ident(&powerset_enum.generic_prefixes.slot, v.idx))
             }
-        });
+        }));
         let add_possibility_match_arms = replaced_variants.iter().filter(|v| v.idx != *idx).map(|v| {
             let variant_ident = &v.variant_ident;
             quote!{
                 #enum_ident::#variant_ident(value) => #enum_ident::#variant_ident(value)
             }
-        });
+        }).chain(powerset_enum.fixed_variants_passthrough_arms(|value| value));
         quote!{
-            impl<#impl_generics> powerset_enum::WithVariant<#ty> for #enum_ident<#(#source_generic_params),*> {
-                type With = #enum_ident<#(#target_generic_params),*>;
+            impl #impl_generics #crate_path::WithVariant<#key> for #source_type #where_clause {
+                type With = #target_type;
                 fn add_possibility(self) -> Self::With {
                     match self {
                         #(#add_possibility_match_arms),*
@@ -207,42 +2053,49 @@ fn gen_with_variant_trait_impls(
                 }
             }
 
-            impl<#impl_generics> powerset_enum::WithVariant<#ty> for #enum_ident<#(#type_exists_source_generic_params),*> {
+            impl #impl_generics #crate_path::WithVariant<#key> for #type_exists_source_type #where_clause {
                 type With = Self;
                 fn add_possibility(self) -> Self::With {
                     self
                 }
             }
         }
-    });
+    }));
     Ok(quote!(#( #impls )*))
 }
 
-fn gen_error_from_trait_impls(
-    enum_ident: &syn::Ident,
-    replaced_variants: &[ReplacedVariant],
-) -> Result<TokenStream, Error> {
-    let impls = replaced_variants.iter().map(|replaced_variant| {
+fn gen_error_from_trait_impls(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let enum_ident = &powerset_enum.ident;
+    let replaced_variants = &powerset_enum.replaced_variants;
+    let where_clause = powerset_enum.where_clause();
+    let impls = replaced_variants.iter().flat_map(|replaced_variant| {
+        replaced_variant.keys().map(move |(key, is_tag)| (replaced_variant, key, is_tag))
+    });
+    let impls = impls.map(|(replaced_variant, key, is_tag)| {
         let ReplacedVariant {
             idx,
             ty,
             variant_ident,
+            ..
         } = &replaced_variant;
-        let impl_generics = replaced_variants
-            .iter()
-            .filter(|v| v.idx != *idx)
-            .map(|v| make_generic_ident("T", v.idx));
-        let generic_params = replaced_variants.iter().map(|v| {
+        let impl_generics = powerset_enum.impl_generics(
+            replaced_variants
+                .iter()
+                .filter(|v| v.idx != *idx)
+                .map(|v| make_generic_ident(&powerset_enum.generic_prefixes.slot, v.idx)),
+        );
+        let enum_type = powerset_enum.ty(replaced_variants.iter().map(|v| {
             if v.idx == *idx {
                 ty.clone()
             } else {
-                make_generic_type(make_generic_ident("T", v.idx))
+                make_generic_type(make_generic_ident(&powerset_enum.generic_prefixes.slot, v.idx))
             }
-        });
+        }));
+        let payload = if is_tag { quote!(value.0) } else { quote!(value) };
         quote! {
-            impl<#(#impl_generics),*> From<#ty> for #enum_ident<#(#generic_params),*> {
-                fn from(value: #ty) -> Self {
-                    #enum_ident::#variant_ident(value)
+            impl #impl_generics From<#key> for #enum_type #where_clause {
+                fn from(value: #key) -> Self {
+                    #enum_ident::#variant_ident(#payload)
                 }
             }
         }
@@ -250,64 +2103,308 @@ fn gen_error_from_trait_impls(
     Ok(quote!(#( #impls )*))
 }
 
-fn gen_without_trait_impls(
-    enum_ident: &syn::Ident,
-    replaced_variants: &[ReplacedVariant],
-) -> Result<TokenStream, Error> {
-    let impls = replaced_variants.iter().map(|replaced_variant| {
+fn gen_without_trait_impls(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let enum_ident = &powerset_enum.ident;
+    let replaced_variants = &powerset_enum.replaced_variants;
+    let where_clause = powerset_enum.where_clause();
+    let impls = replaced_variants.iter().flat_map(|replaced_variant| replaced_variant.keys().map(move |(key, is_tag)| {
         let ReplacedVariant {idx, ty, ..} = &replaced_variant;
-        let impl_generics = replaced_variants.iter().filter(|v| v.idx != *idx).map(|v| make_generic_ident("T", v.idx));
-        let impl_generics = quote!(#(#impl_generics),*);
-        let source_generic_params = replaced_variants.iter().map(|v| {
+        let impl_generics = powerset_enum.impl_generics(replaced_variants.iter().filter(|v| v.idx != *idx).map(|v| make_generic_ident(&powerset_enum.generic_prefixes.slot, v.idx)));
+        let source_type = powerset_enum.ty(replaced_variants.iter().map(|v| {
             if v.idx == *idx {
                 ty.clone()
             } else {
-                make_generic_type(make_generic_ident("T", v.idx))
+                make_generic_type(make_generic_ident(&powerset_enum.generic_prefixes.slot, v.idx))
             }
-        });
-        let target_generic_params = replaced_variants.iter().map(|v| {
+        }));
+        let target_type = powerset_enum.ty(replaced_variants.iter().map(|v| {
             if v.idx == *idx {
-                make_never()
+                make_never(crate_path)
             } else {
-                make_generic_type(make_generic_ident("T", v.idx))
+                make_generic_type(make_generic_ident(&powerset_enum.generic_prefixes.slot, v.idx))
             }
-        });
+        }));
         let extract_match_arms = replaced_variants.iter().map(|v| {
             let variant_ident = &v.variant_ident;
             if v.idx == *idx {
+                let removed = if is_tag {
+                    let tag_path = &v.tag_path;
+                    quote!(#tag_path(value))
+                } else {
+                    quote!(value)
+                };
                 quote!{
-                    #enum_ident::#variant_ident(value) => Err(value)
+                    #enum_ident::#variant_ident(value) => Err(#removed)
                 }
             } else {
                 quote!{
                     #enum_ident::#variant_ident(value) => Ok(#enum_ident::#variant_ident(value))
                 }
             }
-        });
+        }).chain(powerset_enum.fixed_variants_passthrough_arms(|value| quote!(Ok(#value))));
         quote!{
-            impl<#impl_generics> powerset_enum::WithoutVariant<#ty> for #enum_ident<#(#source_generic_params),*> {
-                type Without = #enum_ident<#(#target_generic_params),*>;
-                fn remove_possibility(self) -> Result<Self::Without, #ty> {
+            impl #impl_generics #crate_path::WithoutVariant<#key> for #source_type #where_clause {
+                type Without = #target_type;
+                fn remove_possibility(self) -> Result<Self::Without, #key> {
                     match self {
                         #(#extract_match_arms),*
                     }
                 }
             }
         }
-    });
+    }));
     Ok(quote!(#( #impls )*))
 }
 
-fn gen_powerset_macro(
-    enum_ident: &syn::Ident,
-    replaced_variants: &[ReplacedVariant],
-) -> Result<TokenStream, Error> {
-    let empty_powerset_generics = replaced_variants.iter().map(|_| make_never());
-    let empty_powerset = quote!(#enum_ident<#(#empty_powerset_generics),*>);
+fn gen_powerset_macro(powerset_enum: &PowersetEnum, args: &PowersetEnumArgs) -> Result<TokenStream, Error> {
+    let PowersetEnum {ident: enum_ident, payloads_module_ident, ..} = powerset_enum;
+    // An exported macro is used from other crates, so it must reach everything through `$crate`.
+    let (enum_path, crate_path, macro_attrs) = if let Some(module) = &args.exported_module {
+        (
+            quote!(#module::#enum_ident),
+            quote!(#module::#payloads_module_ident::__powerset_enum),
+            quote!(#[macro_export]),
+        )
+    } else {
+        let crate_path = &powerset_enum.crate_path;
+        (quote!(#enum_ident), quote!(#crate_path), quote!())
+    };
+    let empty_powerset_generics = powerset_enum.replaced_variants.iter().map(|_| quote!(#crate_path::Never));
+    let empty_powerset = quote!(#enum_path<#(#empty_powerset_generics),*>);
+
+    // The variants table lets `powerset!` resolve variant names to their slots.
+    let generic_params = powerset_enum.generics.params.iter().map(|param| match param {
+        syn::GenericParam::Type(param) => param.ident.clone().into_token_stream(),
+        syn::GenericParam::Lifetime(param) => param.lifetime.clone().into_token_stream(),
+        syn::GenericParam::Const(param) => param.ident.clone().into_token_stream(),
+    });
+    // The payload type as written may not be nameable where the macro is used, so names resolve to
+    // its alias in the exported module - or, since the module of a macro that is not exported is
+    // not known, to the payload of the slot through the `enum` itself.
+    let any_subset_args = generic_params.clone().chain(powerset_enum.replaced_variants.iter().map(|_| quote!(())));
+    let any_subset = quote!(#enum_path<#(#any_subset_args),*>);
+    let slot_entries = powerset_enum.replaced_variants.iter().map(|v| {
+        let ReplacedVariant {idx, ty, tag_generics, variant_ident, ..} = v;
+        let idx = syn::LitInt::new(*idx as u64, syn::IntSuffix::None, proc_macro2::Span::call_site());
+        if let Some(module) = &args.exported_module {
+            let payload_alias_ident = make_payload_alias_ident(variant_ident);
+            let (_, type_generics, _) = tag_generics.split_for_impl();
+            quote!(#variant_ident(#idx: #ty = #module::#payloads_module_ident::#payload_alias_ident #type_generics))
+        } else {
+            quote!(#variant_ident(#idx: #ty = #crate_path::Payload<#any_subset, #idx>))
+        }
+    });
+    let fixed_entries = powerset_enum.fixed_variants.iter().map(|FixedVariant {variant_ident, type_param}| {
+        quote!(#variant_ident(#type_param))
+    });
+    let entries = slot_entries.chain(fixed_entries);
     Ok(quote! {
+        #macro_attrs
         macro_rules! #enum_ident {
-            ($($tt:ty),*) => { powerset_enum::powerset!(#empty_powerset, $($tt),*) };
-            ($($tt:ty),*,) => { powerset_enum::powerset!(#empty_powerset, $($tt),*) };
+            ($($tt:tt)*) => {
+                #crate_path::powerset!(#empty_powerset, @variants(#crate_path)<#(#generic_params),*> { #(#entries),* } $($tt)*)
+            };
+        }
+    })
+}
+
+fn gen_error_trait_impls(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let num_slots = powerset_enum.replaced_variants.len();
+    let impl_generics = powerset_enum.impl_generics(make_generic_idents(&powerset_enum.generic_prefixes.slot, 0..num_slots));
+    let enum_type = powerset_enum.ty(make_generic_idents(&powerset_enum.generic_prefixes.slot, 0..num_slots));
+    let payload_types: Vec<_> = make_generic_idents(&powerset_enum.generic_prefixes.slot, 0..num_slots)
+        .chain(powerset_enum.fixed_variants.iter().map(|v| v.type_param.clone()))
+        .collect();
+    let display_where_clause = with_predicates(
+        powerset_enum.where_clause(),
+        payload_types.iter().map(|ty| syn::parse_quote!(#ty: core::fmt::Display)),
+    );
+    let error_where_clause = with_predicates(
+        powerset_enum.where_clause(),
+        payload_types
+            .iter()
+            .map(|ty| syn::parse_quote!(#ty: std::error::Error))
+            .chain(Some(syn::parse_quote!(Self: core::fmt::Debug))),
+    );
+    let display_match = powerset_enum.ref_match(|_| quote!(core::fmt::Display::fmt(value, f)));
+    let source_match = powerset_enum.ref_match(|_| quote!(std::error::Error::source(value)));
+    Ok(quote! {
+        impl #impl_generics core::fmt::Display for #enum_type #display_where_clause {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                #display_match
+            }
+        }
+
+        impl #impl_generics std::error::Error for #enum_type #error_where_clause {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                #source_match
+            }
+        }
+    })
+}
+
+fn gen_serde_trait_impls(powerset_enum: &PowersetEnum) -> Result<TokenStream, Error> {
+    let crate_path = &powerset_enum.crate_path;
+    let serde_support = quote!(#crate_path::serde_support);
+    let serde = quote!(#serde_support::serde);
+    let never = make_never(crate_path);
+    let enum_ident = &powerset_enum.ident;
+    let enum_name = enum_ident.to_string();
+    let num_slots = powerset_enum.replaced_variants.len();
+    let slot_idents: Vec<_> = make_generic_idents(&powerset_enum.generic_prefixes.slot, 0..num_slots).collect();
+    let enum_type = powerset_enum.ty(&slot_idents);
+    let where_clause = powerset_enum.where_clause();
+    let format_param = syn::Ident::new(&powerset_enum.generic_prefixes.subset, proc_macro2::Span::call_site());
+    let de_lifetime = syn::Lifetime::new("'__powerset_enum_de", proc_macro2::Span::call_site());
+
+    let slot_impls = powerset_enum.replaced_variants.iter().map(|v| {
+        let ReplacedVariant {ty, tag, tag_generics, variant_ident, ..} = v;
+        let variant_name = variant_ident.to_string();
+        let index = powerset_enum.variant_position(variant_ident) as u32;
+        let (impl_generics, _, tag_where_clause) = tag_generics.split_for_impl();
+        let serialize_where_clause = with_predicates(tag_where_clause, Some(syn::parse_quote!(#ty: #serde::Serialize)));
+        let mut de_generics = tag_generics.clone();
+        de_generics.params.insert(0, syn::GenericParam::Lifetime(syn::LifetimeDef::new(de_lifetime.clone())));
+        let (de_impl_generics, _, _) = de_generics.split_for_impl();
+        let deserialize_where_clause = with_predicates(tag_where_clause, Some(syn::parse_quote!(#ty: #serde::Deserialize<#de_lifetime>)));
+        quote! {
+            impl #impl_generics #serde_support::SerializeSlot<#ty> for #tag #serialize_where_clause {
+                fn serialize_slot<#format_param: #serde::Serializer>(
+                    slot: &#ty,
+                    serializer: #format_param,
+                ) -> Result<#format_param::Ok, #format_param::Error> {
+                    serializer.serialize_newtype_variant(#enum_name, #index, #variant_name, slot)
+                }
+            }
+
+            impl #impl_generics #serde_support::SerializeSlot<#never> for #tag #tag_where_clause {
+                fn serialize_slot<#format_param: #serde::Serializer>(
+                    slot: &#never,
+                    _: #format_param,
+                ) -> Result<#format_param::Ok, #format_param::Error> {
+                    match *slot {}
+                }
+            }
+
+            impl #de_impl_generics #serde_support::DeserializeSlot<#de_lifetime, #ty> for #tag #deserialize_where_clause {
+                fn deserialize_slot<#format_param: #serde::de::VariantAccess<#de_lifetime>>(
+                    variant: #format_param,
+                    _: &'static [&'static str],
+                ) -> Result<#ty, #format_param::Error> {
+                    variant.newtype_variant()
+                }
+            }
+
+            impl #de_impl_generics #serde_support::DeserializeSlot<#de_lifetime, #never> for #tag #tag_where_clause {
+                fn deserialize_slot<#format_param: #serde::de::VariantAccess<#de_lifetime>>(
+                    _: #format_param,
+                    expected: &'static [&'static str],
+                ) -> Result<#never, #format_param::Error> {
+                    Err(#serde::de::Error::unknown_variant(#variant_name, expected))
+                }
+            }
+        }
+    });
+
+    let impl_generics = powerset_enum.impl_generics(slot_idents.iter().cloned());
+    let serialize_where_clause = with_predicates(
+        where_clause,
+        powerset_enum.replaced_variants.iter().map(|v| -> syn::WherePredicate {
+            let tag = &v.tag;
+            let slot = &slot_idents[v.idx];
+            syn::parse_quote!(#tag: #serde_support::SerializeSlot<#slot>)
+        }).chain(powerset_enum.fixed_variants.iter().map(|v| -> syn::WherePredicate {
+            let type_param = &v.type_param;
+            syn::parse_quote!(#type_param: #serde::Serialize)
+        })),
+    );
+    let serialize_match = powerset_enum.ref_match(|variant_ident| {
+        match powerset_enum.replaced_variants.iter().find(|v| v.variant_ident == *variant_ident) {
+            Some(ReplacedVariant {tag, idx, ..}) => {
+                let slot = &slot_idents[*idx];
+                quote!(<#tag as #serde_support::SerializeSlot<#slot>>::serialize_slot(value, serializer))
+            }
+            None => {
+                let variant_name = variant_ident.to_string();
+                let index = powerset_enum.variant_position(variant_ident) as u32;
+                quote!(serializer.serialize_newtype_variant(#enum_name, #index, #variant_name, value))
+            }
+        }
+    });
+
+    let de_impl_generics = {
+        let impl_generics = powerset_enum.impl_generics(slot_idents.iter().cloned());
+        let mut generics: syn::Generics = syn::parse_quote!(#impl_generics);
+        generics.params.insert(0, syn::GenericParam::Lifetime(syn::LifetimeDef::new(de_lifetime.clone())));
+        generics
+    };
+    let (de_impl_generics, _, _) = de_impl_generics.split_for_impl();
+    let deserialize_where_clause = with_predicates(
+        where_clause,
+        powerset_enum.replaced_variants.iter().map(|v| -> syn::WherePredicate {
+            let tag = &v.tag;
+            let slot = &slot_idents[v.idx];
+            syn::parse_quote!(#tag: #serde_support::DeserializeSlot<#de_lifetime, #slot>)
+        }).chain(powerset_enum.presence_bounds(&slot_idents)).chain(powerset_enum.fixed_variants.iter().map(|v| -> syn::WherePredicate {
+            let type_param = &v.type_param;
+            syn::parse_quote!(#type_param: #serde::Deserialize<#de_lifetime>)
+        })),
+    );
+    let variant_names = powerset_enum.variant_idents.iter().map(|ident| ident.to_string());
+    let deserialize_arms = powerset_enum.variant_idents.iter().enumerate().map(|(index, variant_ident)| {
+        match powerset_enum.replaced_variants.iter().find(|v| v.variant_ident == *variant_ident) {
+            Some(ReplacedVariant {tag, idx, ..}) => {
+                let slot = &slot_idents[*idx];
+                quote! {
+                    #index => <#tag as #serde_support::DeserializeSlot<#de_lifetime, #slot>>::deserialize_slot(
+                        variant,
+                        Self::VARIANT_NAMES,
+                    ).map(#enum_ident::#variant_ident)
+                }
+            }
+            None => quote! {
+                #index => #serde::de::VariantAccess::newtype_variant(variant).map(#enum_ident::#variant_ident)
+            },
+        }
+    });
+
+    Ok(quote! {
+        #(#slot_impls)*
+
+        impl #impl_generics #serde::Serialize for #enum_type #serialize_where_clause {
+            fn serialize<#format_param: #serde::Serializer>(
+                &self,
+                serializer: #format_param,
+            ) -> Result<#format_param::Ok, #format_param::Error> {
+                #serialize_match
+            }
+        }
+
+        impl #de_impl_generics #serde_support::DeserializeVariant<#de_lifetime> for #enum_type #deserialize_where_clause {
+            const NAME: &'static str = #enum_name;
+            const VARIANTS: &'static [&'static str] = &[#(#variant_names),*];
+            const PRESENT_VARIANTS: &'static [&'static str] = Self::VARIANT_NAMES;
+
+            fn deserialize_variant<#format_param: #serde::de::VariantAccess<#de_lifetime>>(
+                index: usize,
+                variant: #format_param,
+            ) -> Result<Self, #format_param::Error> {
+                match index {
+                    #(#deserialize_arms,)*
+                    _ => unreachable!("the index is of one of the variants"),
+                }
+            }
+        }
+
+        impl #de_impl_generics #serde::Deserialize<#de_lifetime> for #enum_type
+        where Self: #serde_support::DeserializeVariant<#de_lifetime>
+        {
+            fn deserialize<#format_param: #serde::Deserializer<#de_lifetime>>(
+                deserializer: #format_param,
+            ) -> Result<Self, #format_param::Error> {
+                #serde_support::deserialize(deserializer)
+            }
         }
     })
 }
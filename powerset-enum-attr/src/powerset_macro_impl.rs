@@ -1,34 +1,495 @@
-use proc_macro2::TokenStream;
+use std::collections::HashMap;
 
-use quote::quote;
+use proc_macro2::{TokenStream, TokenTree};
+
+use quote::{quote, ToTokens};
+use syn::parse::discouraged::Speculative;
 use syn::parse::Error;
+use syn::punctuated::Punctuated;
 
 #[derive(Debug)]
 pub struct PowersetMacroInput {
     empty_powerset: syn::Type,
-    comma: Option<syn::token::Comma>,
-    types_in_powerset: syn::punctuated::Punctuated<syn::Type, syn::token::Comma>,
+    _comma: Option<syn::token::Comma>,
+    variants_table: VariantsTable,
+    generic_args: Punctuated<syn::GenericArgument, syn::token::Comma>,
+    types_in_powerset: Punctuated<PowersetElement, syn::token::Comma>,
+}
+
+#[derive(Debug)]
+enum PowersetElement {
+    /// A variant, by its type or by its name.
+    Variant(syn::Type),
+    /// `..Subset` - all the variants of another subset.
+    Subset(syn::Type),
+}
+
+impl syn::parse::Parse for PowersetElement {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self, Error> {
+        if input.peek(syn::token::Dot2) {
+            input.parse::<syn::token::Dot2>()?;
+            Ok(PowersetElement::Subset(input.parse()?))
+        } else {
+            Ok(PowersetElement::Variant(input.parse()?))
+        }
+    }
 }
 
 impl syn::parse::Parse for PowersetMacroInput {
     fn parse(input: syn::parse::ParseStream) -> Result<Self, Error> {
         Ok(PowersetMacroInput {
             empty_powerset: input.parse()?,
-            comma: input.parse()?,
-            types_in_powerset: syn::punctuated::Punctuated::parse_terminated(input)?,
+            _comma: input.parse()?,
+            variants_table: input.parse()?,
+            generic_args: parse_generic_args(input)?,
+            types_in_powerset: Punctuated::parse_terminated(input)?,
+        })
+    }
+}
+
+/// The `@variants(crate_path)<'a, B> { Name(0: Payload = Resolved), Fixed(B) }` table the macros generated
+/// by `#[powerset_enum]` pass to `powerset!`, so that it can resolve variant names.
+#[derive(Debug)]
+struct VariantsTable {
+    /// The path of the powerset-enum crate.
+    crate_path: syn::Path,
+    generics: syn::Generics,
+    entries: Punctuated<VariantsTableEntry, syn::token::Comma>,
+}
+
+impl syn::parse::Parse for VariantsTable {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self, Error> {
+        input.parse::<syn::token::At>()?;
+        let keyword: syn::Ident = input.parse()?;
+        if keyword != "variants" {
+            return Err(Error::new_spanned(keyword, "expected `variants`"));
+        }
+        let crate_path;
+        syn::parenthesized!(crate_path in input);
+        let crate_path = crate_path.call(syn::Path::parse_mod_style)?;
+        let generics = input.parse()?;
+        let content;
+        syn::braced!(content in input);
+        Ok(VariantsTable {
+            crate_path,
+            generics,
+            entries: Punctuated::parse_terminated(&content)?,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct VariantsTableEntry {
+    variant_ident: syn::Ident,
+    kind: VariantKind,
+}
+
+#[derive(Debug)]
+enum VariantKind {
+    /// A variant with a slot parameter, given by its index among the slot parameters. The types
+    /// listed in the macro are matched against the payload type as written, but the slot is filled
+    /// with the `= resolved` type, which can be named wherever the macro is used.
+    Slot {
+        idx: usize,
+        payload: syn::Type,
+        resolved: Box<syn::Type>,
+    },
+    /// A variant whose payload type is one of the `enum`'s own type parameters.
+    Fixed { type_param: syn::Ident },
+}
+
+impl syn::parse::Parse for VariantsTableEntry {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self, Error> {
+        let variant_ident = input.parse()?;
+        let content;
+        syn::parenthesized!(content in input);
+        let kind = if content.peek(syn::LitInt) {
+            let idx: syn::LitInt = content.parse()?;
+            content.parse::<syn::token::Colon>()?;
+            let payload = content.parse()?;
+            content.parse::<syn::token::Eq>()?;
+            let resolved = Box::new(content.parse()?);
+            VariantKind::Slot {
+                idx: idx.value() as usize,
+                payload,
+                resolved,
+            }
+        } else {
+            VariantKind::Fixed {
+                type_param: content.parse()?,
+            }
+        };
+        Ok(VariantsTableEntry {
+            variant_ident,
+            kind,
         })
     }
 }
 
+/// Parse the optional `'a, B;` prefix that provides the arguments for the `enum`'s own generic
+/// parameters.
+fn parse_generic_args(
+    input: syn::parse::ParseStream,
+) -> Result<Punctuated<syn::GenericArgument, syn::token::Comma>, Error> {
+    let fork = input.fork();
+    if let Ok(generic_args) = Punctuated::parse_separated_nonempty(&fork) {
+        if fork.peek(syn::token::Semi) {
+            fork.parse::<syn::token::Semi>()?;
+            input.advance_to(&fork);
+            return Ok(generic_args);
+        }
+    }
+    Ok(Punctuated::new())
+}
+
 pub fn powerset_macro_impl(input: PowersetMacroInput) -> Result<TokenStream, Error> {
     let PowersetMacroInput {
-        empty_powerset,
+        mut empty_powerset,
+        variants_table,
+        generic_args,
         types_in_powerset,
         ..
     } = input;
+    let mut slot_args = Vec::new();
+    let mut projected_types = Vec::new();
+    let mut subsets = Vec::new();
+    for element in types_in_powerset {
+        let ty = match element {
+            PowersetElement::Variant(ty) => ty,
+            PowersetElement::Subset(subset) => {
+                subsets.push(subset);
+                continue;
+            }
+        };
+        match variants_table.resolve(&ty, &generic_args)? {
+            Resolved::Slot(idx, payload) => slot_args.push((idx, payload)),
+            Resolved::Never => {}
+            Resolved::Unknown => projected_types.push(ty),
+        }
+    }
+    if !generic_args.is_empty() {
+        prepend_generic_args(&mut empty_powerset, generic_args)?;
+    }
+    set_slot_args(&mut empty_powerset, variants_table.num_slots(), slot_args)?;
+    // Types that could not be matched against the variants table - like aliases or differently
+    // spelled paths - are still added through the `WithVariant` implementations.
+    let crate_path = &variants_table.crate_path;
     let mut result = quote!(#empty_powerset);
-    for ty in types_in_powerset {
-        result = quote!(<#result as powerset_enum::WithVariant<#ty>>::With);
+    for ty in projected_types {
+        result = quote!(<#result as #crate_path::WithVariant<#ty>>::With);
+    }
+    for subset in subsets {
+        result = quote!(<#result as #crate_path::SetOperations<#subset>>::Union);
     }
     Ok(result)
 }
+
+/// What a type listed in the macro was resolved to.
+enum Resolved {
+    /// The slot index and payload type of a variant.
+    Slot(usize, syn::Type),
+    /// The `Never` type, which does not add anything.
+    Never,
+    /// A type that does not textually match anything in the variants table.
+    Unknown,
+}
+
+impl VariantsTable {
+    fn num_slots(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| match entry.kind {
+                VariantKind::Slot { .. } => true,
+                VariantKind::Fixed { .. } => false,
+            })
+            .count()
+    }
+
+    /// Resolve `ty` by variant name, by tag or by payload type - in that order.
+    fn resolve(
+        &self,
+        ty: &syn::Type,
+        generic_args: &Punctuated<syn::GenericArgument, syn::token::Comma>,
+    ) -> Result<Resolved, Error> {
+        let substitutions = self.substitutions(generic_args);
+        if let Some(entry) = self.entry_named_by(ty) {
+            let name = type_key(ty);
+            let other_variant_with_that_payload = self.entries.iter().find(|other| match &other.kind {
+                VariantKind::Slot { payload, .. } => {
+                    other.variant_ident != entry.variant_ident && type_key(payload) == name
+                }
+                VariantKind::Fixed { .. } => false,
+            });
+            if let Some(other) = other_variant_with_that_payload {
+                return Err(Error::new_spanned(
+                    ty,
+                    format!(
+                        "`{}` is ambiguous - it is both the name of a variant and the payload type of the `{}` variant",
+                        name, other.variant_ident,
+                    ),
+                ));
+            }
+            return self.resolve_entry(entry, ty, &substitutions);
+        }
+        if self.is_never(ty) {
+            return Ok(Resolved::Never);
+        }
+        if let Some(entry) = self.entry_tagged_by(ty) {
+            return self.resolve_entry(entry, ty, &substitutions);
+        }
+        let key = type_key(ty);
+        let mut matching_slots = Vec::new();
+        for entry in self.entries.iter() {
+            if let VariantKind::Slot { idx, payload, resolved } = &entry.kind {
+                if type_key(&substitute_payload(payload, &substitutions)?) == key {
+                    let resolved = substitute_payload(resolved, &substitutions)?;
+                    matching_slots.push((entry, *idx, resolved));
+                }
+            }
+        }
+        if 1 < matching_slots.len() {
+            let variant_names: Vec<String> = matching_slots
+                .iter()
+                .map(|(entry, ..)| format!("`{}`", entry.variant_ident))
+                .collect();
+            return Err(Error::new_spanned(
+                ty,
+                format!(
+                    "`{}` is the payload type of several variants ({}) - list them by name instead",
+                    key,
+                    variant_names.join(", "),
+                ),
+            ));
+        }
+        Ok(match matching_slots.pop() {
+            Some((_, idx, payload)) => Resolved::Slot(idx, payload),
+            None => Resolved::Unknown,
+        })
+    }
+
+    fn resolve_entry(
+        &self,
+        entry: &VariantsTableEntry,
+        ty: &syn::Type,
+        substitutions: &HashMap<String, TokenStream>,
+    ) -> Result<Resolved, Error> {
+        match &entry.kind {
+            VariantKind::Slot { idx, resolved, .. } => Ok(Resolved::Slot(
+                *idx,
+                substitute_payload(resolved, substitutions)?,
+            )),
+            VariantKind::Fixed { type_param } => Err(Error::new_spanned(
+                ty,
+                format!(
+                    "the `{}` variant is included by the argument for `{}`, and excluded by passing `Never` for it",
+                    entry.variant_ident, type_param,
+                ),
+            )),
+        }
+    }
+
+    /// Whether `ty` is `!`, `Never` or `Never` under the crate's path.
+    fn is_never(&self, ty: &syn::Type) -> bool {
+        if let syn::Type::Never(_) = ty {
+            return true;
+        }
+        let crate_path = &self.crate_path;
+        let without_leading_colons = |key: String| key.trim_start_matches(": : ").to_owned();
+        let key = without_leading_colons(type_key(ty));
+        key == "Never" || key == without_leading_colons(type_key(&quote!(#crate_path::Never)))
+    }
+
+    /// The entry of the variant `ty` names, if `ty` is a plain identifier.
+    fn entry_named_by(&self, ty: &syn::Type) -> Option<&VariantsTableEntry> {
+        let path = plain_path(ty)?;
+        if path.leading_colon.is_some() || path.segments.len() != 1 {
+            return None;
+        }
+        let ident = &path.segments.first()?.into_value().ident;
+        self.entries
+            .iter()
+            .find(|entry| entry.variant_ident == *ident)
+    }
+
+    /// The entry of the variant whose tag is `ty`, if `ty` is a path through a `tags` module.
+    fn entry_tagged_by(&self, ty: &syn::Type) -> Option<&VariantsTableEntry> {
+        let path = plain_path(ty)?;
+        let segments: Vec<_> = path.segments.iter().collect();
+        let (last, rest) = segments.split_last()?;
+        if rest.last()?.ident != "tags" {
+            return None;
+        }
+        let ident = &last.ident;
+        self.entries
+            .iter()
+            .find(|entry| entry.variant_ident == *ident)
+    }
+
+    /// Map the `enum`'s generic parameters to the arguments given for them.
+    fn substitutions(
+        &self,
+        generic_args: &Punctuated<syn::GenericArgument, syn::token::Comma>,
+    ) -> HashMap<String, TokenStream> {
+        self.generics
+            .params
+            .iter()
+            .zip(generic_args)
+            .map(|(param, arg)| {
+                let name = match param {
+                    syn::GenericParam::Type(param) => param.ident.to_string(),
+                    syn::GenericParam::Lifetime(param) => param.lifetime.to_string(),
+                    syn::GenericParam::Const(param) => param.ident.to_string(),
+                };
+                (name, arg.into_token_stream())
+            })
+            .collect()
+    }
+}
+
+/// The path of `ty`, if it is a path without a qualified self type.
+fn plain_path(ty: &syn::Type) -> Option<&syn::Path> {
+    if let syn::Type::Path(syn::TypePath { qself: None, path }) = ty {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// A textual representation of a type that does not depend on the spacing it was written with.
+fn type_key(ty: &impl ToTokens) -> String {
+    fn push_tokens(tokens: TokenStream, key: &mut Vec<String>) {
+        for token in tokens {
+            match token {
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        proc_macro2::Delimiter::Parenthesis => ("(", ")"),
+                        proc_macro2::Delimiter::Brace => ("{", "}"),
+                        proc_macro2::Delimiter::Bracket => ("[", "]"),
+                        proc_macro2::Delimiter::None => ("", ""),
+                    };
+                    key.push(open.to_owned());
+                    push_tokens(group.stream(), key);
+                    key.push(close.to_owned());
+                }
+                token => key.push(token.to_string()),
+            }
+        }
+    }
+    let mut key = Vec::new();
+    push_tokens(ty.into_token_stream(), &mut key);
+    key.retain(|part| !part.is_empty());
+    key.join(" ")
+}
+
+fn substitute_payload(
+    payload: &syn::Type,
+    substitutions: &HashMap<String, TokenStream>,
+) -> Result<syn::Type, Error> {
+    syn::parse2(substitute_generics(
+        payload.into_token_stream(),
+        substitutions,
+    ))
+}
+
+/// Replace the `enum`'s generic parameters in a payload type with the arguments given for them.
+fn substitute_generics(
+    tokens: TokenStream,
+    substitutions: &HashMap<String, TokenStream>,
+) -> TokenStream {
+    let mut result = TokenStream::new();
+    let mut tokens = tokens.into_iter().peekable();
+    // Identifiers that follow a `::` are path segments, not generic parameters.
+    let mut colons_before = 0;
+    while let Some(token) = tokens.next() {
+        let colons = match &token {
+            TokenTree::Punct(punct) if punct.as_char() == ':' => colons_before + 1,
+            _ => 0,
+        };
+        match token {
+            TokenTree::Punct(ref punct) if punct.as_char() == '\'' => {
+                let substitution = if let Some(TokenTree::Ident(ident)) = tokens.peek() {
+                    substitutions.get(&format!("'{}", ident))
+                } else {
+                    None
+                };
+                if let Some(substitution) = substitution {
+                    tokens.next();
+                    result.extend(substitution.clone());
+                } else {
+                    result.extend(Some(token));
+                }
+            }
+            TokenTree::Ident(ref ident) if colons_before < 2 => {
+                if let Some(substitution) = substitutions.get(&ident.to_string()) {
+                    result.extend(substitution.clone());
+                } else {
+                    result.extend(Some(token));
+                }
+            }
+            TokenTree::Group(group) => {
+                let mut substituted = proc_macro2::Group::new(
+                    group.delimiter(),
+                    substitute_generics(group.stream(), substitutions),
+                );
+                substituted.set_span(group.span());
+                result.extend(Some(TokenTree::Group(substituted)));
+            }
+            token => result.extend(Some(token)),
+        }
+        colons_before = colons;
+    }
+    result
+}
+
+/// Fill the slots of the empty powerset with the payload types of the variants named in the
+/// macro.
+fn set_slot_args(
+    empty_powerset: &mut syn::Type,
+    num_slots: usize,
+    slot_args: Vec<(usize, syn::Type)>,
+) -> Result<(), Error> {
+    let arguments = angle_bracketed_args(empty_powerset)?;
+    let first_slot = arguments.args.len() - num_slots;
+    for (idx, payload) in slot_args {
+        if let Some(slot) = arguments.args.iter_mut().nth(first_slot + idx) {
+            *slot = syn::GenericArgument::Type(payload);
+        }
+    }
+    Ok(())
+}
+
+fn prepend_generic_args(
+    empty_powerset: &mut syn::Type,
+    generic_args: Punctuated<syn::GenericArgument, syn::token::Comma>,
+) -> Result<(), Error> {
+    let arguments = angle_bracketed_args(empty_powerset)?;
+    let slot_args = std::mem::replace(&mut arguments.args, generic_args);
+    if !arguments.args.trailing_punct() {
+        arguments.args.push_punct(Default::default());
+    }
+    arguments.args.extend(slot_args);
+    Ok(())
+}
+
+fn angle_bracketed_args(
+    empty_powerset: &mut syn::Type,
+) -> Result<&mut syn::AngleBracketedGenericArguments, Error> {
+    let error = Error::new_spanned(
+        &empty_powerset,
+        "the empty powerset must be a path to the powerset enum with angle-bracketed generic arguments",
+    );
+    let segment = if let syn::Type::Path(syn::TypePath { path, .. }) = empty_powerset {
+        path.segments.last_mut().map(|segment| segment.into_value())
+    } else {
+        None
+    };
+    let segment = segment.ok_or(error.clone())?;
+    if let syn::PathArguments::None = segment.arguments {
+        segment.arguments = syn::PathArguments::AngleBracketed(syn::parse_quote!(<>));
+    }
+    if let syn::PathArguments::AngleBracketed(arguments) = &mut segment.arguments {
+        Ok(arguments)
+    } else {
+        Err(error)
+    }
+}
@@ -0,0 +1,52 @@
+use proc_macro2::TokenStream;
+
+use quote::quote;
+use syn::parse::Error;
+
+use crate::auto_upcast_impl::{parse_crate_path_arg, rewrite_try_expressions};
+
+/// The arguments of the `#[throws(...)]` attribute.
+#[derive(Debug)]
+pub struct ThrowsArgs {
+    /// The path of the powerset-enum crate, for when it is re-exported or renamed.
+    crate_path: syn::Path,
+    /// The path of the subset macro - which is also the `enum`'s name.
+    macro_path: syn::Path,
+    /// Everything after the `:`, passed to the subset macro as is.
+    subset: TokenStream,
+}
+
+impl syn::parse::Parse for ThrowsArgs {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self, Error> {
+        let crate_path = if input.peek(syn::token::Crate) {
+            let crate_path = parse_crate_path_arg(input)?;
+            input.parse::<syn::token::Comma>()?;
+            crate_path
+        } else {
+            syn::parse_quote!(::powerset_enum)
+        };
+        let macro_path = syn::Path::parse_mod_style(input)?;
+        input.parse::<syn::token::Colon>()?;
+        let subset = input.parse()?;
+        Ok(ThrowsArgs { crate_path, macro_path, subset })
+    }
+}
+
+pub fn throws_impl(args: ThrowsArgs, mut input: syn::ItemFn) -> Result<TokenStream, Error> {
+    let ThrowsArgs { crate_path, macro_path, subset } = args;
+
+    let ok_type = match input.decl.output {
+        syn::ReturnType::Default => syn::parse_quote!(()),
+        syn::ReturnType::Type(_, ref ty) => (**ty).clone(),
+    };
+    let return_type: syn::Type = syn::parse_quote!(core::result::Result<#ok_type, #macro_path![#subset]>);
+    input.decl.output = syn::parse_quote!(-> #return_type);
+
+    rewrite_try_expressions(&mut input.block, &crate_path, return_type);
+    let block = &input.block;
+    *input.block = syn::parse_quote!({
+        core::result::Result::Ok(#block)
+    });
+
+    Ok(quote!(#input))
+}
@@ -0,0 +1,28 @@
+//! Support for the HTTP statuses given with `#[powerset(status = ...)]`, and for the
+//! `IntoResponse` implementations generated by `#[powerset_enum(axum)]`.
+
+#[cfg(feature = "axum")]
+pub use axum;
+#[cfg(feature = "axum")]
+pub use axum::http;
+#[cfg(not(feature = "axum"))]
+pub use http;
+
+use core::fmt::Display;
+
+use http::StatusCode;
+
+/// Converts a status that `#[powerset_enum]` already checked to be in range.
+pub fn status(code: u16) -> StatusCode {
+    StatusCode::from_u16(code).expect("statuses are checked by #[powerset_enum]")
+}
+
+/// The body of the response for an error with `status`: the error's message for client errors,
+/// and only the canonical reason for server errors, whose messages may reveal internals.
+pub fn response_body(status: StatusCode, error: &dyn Display) -> String {
+    if status.is_server_error() {
+        status.canonical_reason().unwrap_or_default().to_owned()
+    } else {
+        error.to_string()
+    }
+}
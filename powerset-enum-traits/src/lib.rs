@@ -1,8 +1,40 @@
+#![cfg_attr(feature = "nightly", feature(never_type))]
+
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub mod serde_support;
+
+#[cfg(any(feature = "http", feature = "axum"))]
+#[doc(hidden)]
+pub mod http_support;
+
+/// The uninhabited type used for the variants that are absent from a powerset.
+///
+/// On stable Rust this is [core::convert::Infallible]. With the `nightly` feature it is the never
+/// type `!`.
+#[cfg(not(feature = "nightly"))]
+pub type Never = core::convert::Infallible;
+
+/// The uninhabited type used for the variants that are absent from a powerset.
+///
+/// On stable Rust this is [core::convert::Infallible]. With the `nightly` feature it is the never
+/// type `!`.
+#[cfg(feature = "nightly")]
+pub type Never = !;
+
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` has no variant that can be addressed by `{T}`",
+    note = "variants that share their payload type with other variants can only be addressed by their tags"
+)]
 pub trait WithVariant<T> {
     type With;
     fn add_possibility(self) -> Self::With;
 }
 
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` has no possible variant that can be addressed by `{V}`",
+    note = "variants that share their payload type with other variants can only be addressed by their tags"
+)]
 pub trait WithoutVariant<V> {
     type Without;
     fn remove_possibility(self) -> Result<Self::Without, V>;
@@ -35,3 +67,373 @@ impl<T> Extract for T {
         self.remove_possibility()
     }
 }
+
+/// Implemented by uninhabited types - including the empty subsets of the `enum`s decorated by
+/// `#[powerset_enum]`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` may still have variants that were not handled",
+    note = "`finish` and `match_powerset!` need all the possible variants to be handled"
+)]
+pub trait Empty {
+    /// Convert a value that cannot exist to any type.
+    fn absurd<T>(self) -> T;
+}
+
+impl Empty for Never {
+    fn absurd<T>(self) -> T {
+        match self {}
+    }
+}
+
+/// Like [Empty], but implemented by the subsets of the `enum`s decorated by `#[powerset_enum]`
+/// slot by slot with [SlotHandled] - so that `match_powerset!` can name the variants it has no
+/// arms for.
+#[doc(hidden)]
+pub trait Handled {
+    fn absurd<T>(self) -> T;
+}
+
+impl Handled for Never {
+    fn absurd<T>(self) -> T {
+        match self {}
+    }
+}
+
+/// Implemented by the variants' tags for the [Never] in the slots of the subsets without them.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "match_powerset! has no arm for `{Slot}`",
+    label = "`{Slot}` is not handled",
+    note = "add an arm for `{Slot}`, or a `_` arm for the variants left"
+)]
+pub trait SlotHandled<Slot> {
+    fn absurd<T>(slot: Slot) -> T;
+}
+
+/// Implemented by every subset of the `enum`s decorated by `#[powerset_enum]`, for code that is
+/// generic over them.
+pub trait Powerset: Sized {
+    /// The subset with all the variants of the `enum`.
+    type Full: Powerset<Full = Self::Full, Empty = Self::Empty>;
+
+    /// The subset with none of the variants of the `enum` - except for those holding the `enum`'s
+    /// own type parameters, which are [Empty] only when these parameters are.
+    type Empty: Powerset<Full = Self::Full, Empty = Self::Empty>;
+
+    /// The number of variants that are possible in this subset.
+    const VARIANT_COUNT: usize;
+
+    /// The names of the variants that are possible in this subset, in the order they were
+    /// declared.
+    const VARIANT_NAMES: &'static [&'static str];
+}
+
+/// Provides methods for handling the variants of the error of a [Result] one at a time:
+///
+/// ```ignore
+/// let value = result
+///     .handle(|a: A| ...)
+///     .handle(|b: B| ...)
+///     .finish();
+/// ```
+pub trait Handle<T, E>: Sized {
+    /// Recover from the variant `V` using `handler`, removing it from the possible errors.
+    fn handle<V, F>(self, handler: F) -> Result<T, <E as WithoutVariant<V>>::Without>
+    where
+        E: WithoutVariant<V>,
+        F: FnOnce(V) -> T;
+
+    /// Get the value once all the variants of the error were handled.
+    fn finish(self) -> T
+    where E: Empty;
+}
+
+impl<T, E> Handle<T, E> for Result<T, E> {
+    fn handle<V, F>(self, handler: F) -> Result<T, <E as WithoutVariant<V>>::Without>
+    where
+        E: WithoutVariant<V>,
+        F: FnOnce(V) -> T,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(err) => match err.remove_possibility() {
+                Ok(remaining_err) => Err(remaining_err),
+                Err(variant) => Ok(handler(variant)),
+            },
+        }
+    }
+
+    fn finish(self) -> T
+    where E: Empty,
+    {
+        match self {
+            Ok(ok) => ok,
+            Err(err) => err.absurd(),
+        }
+    }
+}
+
+/// Narrows a subset to a smaller subset, keeping the variants that don't fit in it apart.
+///
+/// Implemented by the `enum`s decorated by `#[powerset_enum]`, which also get an inherent
+/// `try_narrow` method with the target as a generic parameter, and by [Result] with them as the
+/// error.
+pub trait Narrow<Target>: Sized {
+    /// The subset of the variants that are not in `Target`.
+    type Rest;
+    fn try_narrow(self) -> Result<Target, Self::Rest>;
+}
+
+impl<T, E, Target> Narrow<Target> for Result<T, E>
+where E: Narrow<Target>,
+{
+    type Rest = Result<T, <E as Narrow<Target>>::Rest>;
+    fn try_narrow(self) -> Result<Target, Self::Rest> {
+        match self {
+            Ok(ok) => Err(Ok(ok)),
+            Err(err) => match err.try_narrow() {
+                Ok(narrowed) => Ok(narrowed),
+                Err(rest) => Err(Err(rest)),
+            },
+        }
+    }
+}
+
+/// Provides a `split` method for splitting a subset - or a [Result] with a subset as its error -
+/// into the variants of another subset and the rest.
+///
+/// Implemented by the `enum`s decorated by `#[powerset_enum]` and by [Result].
+pub trait Split: Sized {
+    fn split<Target>(self) -> Result<Target, <Self as Narrow<Target>>::Rest>
+    where Self: Narrow<Target>
+    {
+        self.try_narrow()
+    }
+}
+
+impl<T, E> Split for Result<T, E> {}
+
+/// Narrows the slot of a single variant between subsets. Implemented by the variants' tags, and
+/// used by the generated [Narrow] implementations.
+#[doc(hidden)]
+pub trait NarrowSlot<Source, Target> {
+    type Rest;
+    fn narrow_slot(source: Source) -> Result<Target, Self::Rest>;
+}
+
+/// Set operations between two subsets of the same powerset `enum`.
+///
+/// Implemented by the `enum`s decorated by `#[powerset_enum]`. Usually used through the [Union],
+/// [Intersection] and [Difference] aliases.
+pub trait SetOperations<Other> {
+    /// The variants that are in either subset.
+    type Union;
+    /// The variants that are in both subsets.
+    type Intersection;
+    /// The variants that are in `Self` but not in `Other`.
+    type Difference;
+}
+
+/// The subset with the variants that are in either `A` or `B`.
+pub type Union<A, B> = <A as SetOperations<B>>::Union;
+
+/// The subset with the variants that are in both `A` and `B`.
+pub type Intersection<A, B> = <A as SetOperations<B>>::Intersection;
+
+/// The subset with the variants that are in `A` but not in `B`.
+pub type Difference<A, B> = <A as SetOperations<B>>::Difference;
+
+/// Set operations between the slots of a single variant in two subsets. Implemented by the
+/// variants' tags, and used by the generated [SetOperations] implementations.
+#[doc(hidden)]
+pub trait SlotSetOperations<A, B> {
+    type Union;
+    type Intersection;
+    type Difference;
+}
+
+/// Converts a subset to a superset of it.
+///
+/// Implemented by the `enum`s decorated by `#[powerset_enum]`, which also get an inherent `upcast`
+/// method with the superset's slots as generic parameters.
+pub trait Upcast<Target> {
+    fn upcast(self) -> Target;
+}
+
+/// A type-erased error, that the generated `try_from_dyn` method recovers a subset from by
+/// downcasting it to the payload types of the subset's variants.
+///
+/// Implemented by `Box<dyn Error + Send + Sync>`, and with the `anyhow` and `eyre` features by
+/// `anyhow::Error` and `eyre::Report`.
+pub trait DynError: Sized {
+    /// Erase a payload.
+    fn from_payload<T: std::error::Error + Send + Sync + 'static>(payload: T) -> Self;
+
+    /// Get the payload back if it is a `T`, or the error unchanged if it is not.
+    fn downcast_payload<T: std::error::Error + Send + Sync + 'static>(self) -> Result<T, Self>;
+}
+
+impl DynError for Box<dyn std::error::Error + Send + Sync> {
+    fn from_payload<T: std::error::Error + Send + Sync + 'static>(payload: T) -> Self {
+        Box::new(payload)
+    }
+
+    fn downcast_payload<T: std::error::Error + Send + Sync + 'static>(self) -> Result<T, Self> {
+        self.downcast().map(|payload| *payload)
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl DynError for anyhow::Error {
+    fn from_payload<T: std::error::Error + Send + Sync + 'static>(payload: T) -> Self {
+        anyhow::Error::new(payload)
+    }
+
+    fn downcast_payload<T: std::error::Error + Send + Sync + 'static>(self) -> Result<T, Self> {
+        self.downcast()
+    }
+}
+
+#[cfg(feature = "eyre")]
+impl DynError for eyre::Report {
+    fn from_payload<T: std::error::Error + Send + Sync + 'static>(payload: T) -> Self {
+        eyre::Report::new(payload)
+    }
+
+    fn downcast_payload<T: std::error::Error + Send + Sync + 'static>(self) -> Result<T, Self> {
+        self.downcast()
+    }
+}
+
+/// The result of a `main` that exits with the status of its error - subsets get it from
+/// `#[powerset(exit_code = ...)]`, and print their `Display` to stderr.
+///
+/// `main` cannot return a `Result` for this, because `Result` reports its error with `Debug` and
+/// always exits with 1.
+#[derive(Debug)]
+pub struct Exit<E>(pub Result<(), E>);
+
+impl<E> From<Result<(), E>> for Exit<E> {
+    fn from(result: Result<(), E>) -> Self {
+        Exit(result)
+    }
+}
+
+impl<E: std::process::Termination> std::process::Termination for Exit<E> {
+    fn report(self) -> std::process::ExitCode {
+        match self.0 {
+            Ok(()) => std::process::ExitCode::SUCCESS,
+            Err(error) => error.report(),
+        }
+    }
+}
+
+/// Converts the slot of a single variant between subsets. Implemented by the variants' tags, and
+/// used by the generated `upcast` method.
+#[doc(hidden)]
+pub trait UpcastSlot<Source, Target> {
+    fn upcast_slot(source: Source) -> Target;
+}
+
+/// Whether the slot of a single variant holds its payload or [Never] in a subset. Implemented by
+/// the variants' tags, and used by the generated `VARIANT_NAMES` and `CODES` consts.
+#[doc(hidden)]
+pub trait SlotPresence<Slot> {
+    const PRESENT: bool;
+}
+
+/// The payload type of the variant in the slot `INDEX`. Implemented by the `enum` for every
+/// subset, so that the generated macro can name a payload type where it is not in scope.
+#[doc(hidden)]
+pub trait SlotPayload<const INDEX: usize> {
+    type Payload;
+}
+
+#[doc(hidden)]
+pub type Payload<E, const INDEX: usize> = <E as SlotPayload<INDEX>>::Payload;
+
+/// Moves the values of the variants that are present to the front, and counts them - for building
+/// the generated `VARIANT_NAMES` and `CODES` consts. `filler` pads the rest of the array.
+#[doc(hidden)]
+pub const fn present_values<T: Copy, const N: usize>(values: [Option<T>; N], filler: T) -> ([T; N], usize) {
+    let mut present = [filler; N];
+    let mut count = 0;
+    let mut i = 0;
+    while i < N {
+        if let Some(value) = values[i] {
+            present[count] = value;
+            count += 1;
+        }
+        i += 1;
+    }
+    (present, count)
+}
+
+/// Builds the payload of a single variant with [Default], for the generated `from_code` method.
+/// Implemented by the variants' tags - a [Never] slot cannot be built, so its variant is absent.
+#[doc(hidden)]
+pub trait DefaultSlot<Slot> {
+    fn default_slot() -> Option<Slot>;
+}
+
+/// Picks how the error of a `?` rewritten by `#[auto_upcast]` or `#[throws]` is converted into the
+/// function's error `T` - with [Upcast] for subsets that can be upcast to it, and with [From] for
+/// anything else.
+///
+/// `(&TryUpcast::<_, T>::new(&error)).kind()` resolves to [ViaUpcast::kind] when the error can be
+/// upcast to `T`, and to [ViaFrom::kind] otherwise.
+#[doc(hidden)]
+pub struct TryUpcast<'a, E, T>(pub &'a E, pub core::marker::PhantomData<T>);
+
+impl<'a, E, T> TryUpcast<'a, E, T> {
+    pub fn new(error: &'a E) -> Self {
+        TryUpcast(error, core::marker::PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub struct UpcastKind;
+
+impl UpcastKind {
+    pub fn convert<E: Upcast<T>, T>(self, error: E) -> T {
+        error.upcast()
+    }
+}
+
+#[doc(hidden)]
+pub struct FromKind;
+
+impl FromKind {
+    pub fn convert<E, T: From<E>>(self, error: E) -> T {
+        T::from(error)
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaUpcast {
+    fn kind(&self) -> UpcastKind {
+        UpcastKind
+    }
+}
+
+impl<E: Upcast<T>, T> ViaUpcast for TryUpcast<'_, E, T> {}
+
+#[doc(hidden)]
+pub trait ViaFrom {
+    fn kind(&self) -> FromKind {
+        FromKind
+    }
+}
+
+impl<E, T> ViaFrom for &TryUpcast<'_, E, T> {}
+
+/// The error type of a function's `Result`, for naming the target of [TryUpcast] from the
+/// function's signature.
+#[doc(hidden)]
+pub trait ResultError {
+    type Error;
+}
+
+impl<T, E> ResultError for Result<T, E> {
+    type Error = E;
+}
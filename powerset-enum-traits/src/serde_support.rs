@@ -0,0 +1,121 @@
+//! Support for the `Serialize` and `Deserialize` implementations generated by
+//! `#[powerset_enum(serde)]`.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+pub use serde;
+
+use serde::de::{self, EnumAccess, VariantAccess, Visitor};
+use serde::{Deserializer, Serializer};
+
+/// Serializes the slot of a single variant. Implemented by the variants' tags.
+pub trait SerializeSlot<Slot> {
+    fn serialize_slot<S: Serializer>(slot: &Slot, serializer: S) -> Result<S::Ok, S::Error>;
+}
+
+/// Deserializes the slot of a single variant, failing when the variant is absent from the subset.
+/// Implemented by the variants' tags.
+pub trait DeserializeSlot<'de, Slot> {
+    fn deserialize_slot<A: VariantAccess<'de>>(
+        variant: A,
+        expected: &'static [&'static str],
+    ) -> Result<Slot, A::Error>;
+}
+
+/// Implemented by the `enum`s decorated by `#[powerset_enum(serde)]`, for [deserialize].
+pub trait DeserializeVariant<'de>: Sized {
+    const NAME: &'static str;
+    /// The names of all the variants, with the index of each used for it.
+    const VARIANTS: &'static [&'static str];
+    /// The names of the variants that are possible in the subset, for error messages.
+    const PRESENT_VARIANTS: &'static [&'static str];
+    fn deserialize_variant<A: VariantAccess<'de>>(index: usize, variant: A) -> Result<Self, A::Error>;
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: DeserializeVariant<'de>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_enum(T::NAME, T::VARIANTS, EnumVisitor(PhantomData))
+}
+
+/// Deserializes the marker types generated for unit variants.
+pub fn deserialize_unit<'de, T, D>(deserializer: D, name: &'static str) -> Result<T, D::Error>
+where
+    T: Default,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_unit_struct(name, UnitVisitor(name, PhantomData))
+}
+
+struct UnitVisitor<T>(&'static str, PhantomData<T>);
+
+impl<'de, T: Default> Visitor<'de> for UnitVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "unit struct {}", self.0)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<T, E> {
+        Ok(T::default())
+    }
+}
+
+struct EnumVisitor<T>(PhantomData<T>);
+
+impl<'de, T: DeserializeVariant<'de>> Visitor<'de> for EnumVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "enum {}", T::NAME)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<T, A::Error> {
+        let (index, variant) = data.variant_seed(VariantIdentifier::<T>(PhantomData))?;
+        T::deserialize_variant(index, variant)
+    }
+}
+
+/// Deserializes the name - or the index - of a variant into its index.
+struct VariantIdentifier<T>(PhantomData<T>);
+
+impl<'de, T: DeserializeVariant<'de>> de::DeserializeSeed<'de> for VariantIdentifier<T> {
+    type Value = usize;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de, T: DeserializeVariant<'de>> Visitor<'de> for VariantIdentifier<T> {
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("variant identifier")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<usize, E> {
+        if value < T::VARIANTS.len() as u64 {
+            Ok(value as usize)
+        } else {
+            Err(E::invalid_value(de::Unexpected::Unsigned(value), &self))
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<usize, E> {
+        T::VARIANTS
+            .iter()
+            .position(|name| *name == value)
+            .ok_or_else(|| E::unknown_variant(value, T::PRESENT_VARIANTS))
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<usize, E> {
+        match core::str::from_utf8(value) {
+            Ok(value) => self.visit_str(value),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Bytes(value), &self)),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-#![feature(never_type, exhaustive_patterns, proc_macro_hygiene)]
+#![allow(clippy::type_complexity)]
 use powerset_enum::*;
 
 #[derive(Debug, PartialEq)]
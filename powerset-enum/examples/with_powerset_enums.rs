@@ -1,4 +1,4 @@
-#![feature(never_type, exhaustive_patterns, proc_macro_hygiene)]
+#![allow(clippy::enum_variant_names, clippy::type_complexity)]
 use std::path::Path;
 
 use std::fs;
@@ -6,7 +6,7 @@ use std::io;
 
 use serde_derive::Deserialize;
 
-use powerset_enum::powerset_enum;
+use powerset_enum::{powerset_enum, throws};
 use powerset_enum::Extract;
 
 #[derive(Debug)]
@@ -30,12 +30,13 @@ fn load_data_file_unchecked(path: &Path) -> Result<Data, Error![io::Error, serde
     Ok(serde_json::from_reader(file)?)
 }
 
-fn load_data_file(path: &Path) -> Result<Data, Error![io::Error, serde_json::Error, FieldError]> {
-    let data = load_data_file_unchecked(&path).map_err(Error::upcast)?;
+#[throws(Error: io::Error, serde_json::Error, FieldError)]
+fn load_data_file(path: &Path) -> Data {
+    let data = load_data_file_unchecked(path)?;
     if data.field > 100 {
         return Err(Error::FieldError(FieldError));
     }
-    Ok(data)
+    data
 }
 
 fn load_data_file_or_default(path: &Path) -> Result<Data, Error![serde_json::Error, FieldError]> {
@@ -47,7 +48,7 @@ fn load_data_best_effort(path: &Path) -> Data {
     load_data_file_or_default(path).unwrap_or_else(|e| match e {
         Error::JsonError(_) => {
             let content =
-                fs::read_to_string(&path).expect("we managed read it before - why not now?");
+                fs::read_to_string(path).expect("we managed read it before - why not now?");
             if let Some(m) = regex::Regex::new(r"\d+").unwrap().find(&content) {
                 Data {
                     field: m.as_str().parse().unwrap(),
@@ -1,3 +1,4 @@
+#![allow(clippy::enum_variant_names)]
 use std::path::Path;
 
 use std::fs;
@@ -9,6 +10,7 @@ use serde_derive::Deserialize;
 struct FieldError;
 
 #[derive(Debug)]
+#[allow(dead_code)]
 enum Error {
     IoError(io::Error),
     JsonError(serde_json::Error),
@@ -26,7 +28,7 @@ fn load_data_file_unchecked(path: &Path) -> Result<Data, Error> {
 }
 
 fn load_data_file(path: &Path) -> Result<Data, Error> {
-    let data = load_data_file_unchecked(&path)?;
+    let data = load_data_file_unchecked(path)?;
     if data.field < 20 {
         return Err(Error::FieldError(FieldError));
     }
@@ -45,7 +47,7 @@ fn load_data_best_effort(path: &Path) -> Data {
         Error::IoError(e) => unreachable!("IO error should have been impossible, but we got {}", e),
         Error::JsonError(_) => {
             let content =
-                fs::read_to_string(&path).expect("we managed read it before - why not now?");
+                fs::read_to_string(path).expect("we managed read it before - why not now?");
             if let Some(m) = regex::Regex::new(r"\d+").unwrap().find(&content) {
                 Data {
                     field: m.as_str().parse().unwrap(),
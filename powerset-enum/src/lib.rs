@@ -1,19 +1,103 @@
 //! The [`#[powerset_enum]`](../powerset_enum_attr/attr.powerset_enum.html) attribute parametrizes an `enum` to make it a powerset (set of all
 //! subsets), and create a macro with the same name of the `enum` for easy notation of the subsets.
 //!
-//! Note: this is a nightly only crate, and to use it you need to enable the following feature
-//! flags:
+//! The variants that are absent from a subset are filled with the uninhabited [Never] type, so
+//! `match` statements on a subset don't need arms for them. On stable Rust [Never] is
+//! [core::convert::Infallible]. Enabling the `nightly` feature makes it the never type `!`, which
+//! requires `#![feature(never_type)]` in the crate that enables it.
+//!
+//! Each variant of the `enum` decorated by `#[powerset_enum]` is addressed by the type of its
+//! item, so these types should be unique within that `enum`.
+//!
+//! Variants that share their item type with other variants are addressed by their tags instead.
+//! A tag is a tuple struct with the same name of the variant, wrapping its item, generated for
+//! each variant in the `tags` submodule of the module named after the `enum` in snake case.
+//! The shared item itself cannot be converted into a subset - like with `?` - so it must be
+//! wrapped in one of the tags:
 //!
 //! ```ignore
-//! #![feature(never_type, exhaustive_patterns, proc_macro_hygiene)]
+//! #[powerset_enum]
+//! enum Error {
+//!     ReadFailed(io::Error),
+//!     WriteFailed(io::Error),
+//! }
+//!
+//! use error::tags::{ReadFailed, WriteFailed};
+//!
+//! fn foo(...) -> Result<..., Error![ReadFailed, WriteFailed]> {
+//!     Err(ReadFailed(io_error))?
+//! }
+//! ```
+//!
+//! Unit variants, struct-like variants and tuple variants with multiple items are also allowed.
+//! For each of them, a struct with the same name is generated in a module named after the `enum`
+//! in snake case, and the variant is changed to hold it. Unit variants get a zero-sized marker
+//! type, and the other variants get a struct with the same items, deriving whatever the `enum`
+//! derives:
+//!
+//! ```ignore
+//! #[powerset_enum]
+//! enum Error {
+//!     NotFound, // becomes `NotFound(error::NotFound)`
+//!     InvalidRange { min: u32, max: u32 }, // becomes `InvalidRange(error::InvalidRange)`
+//!     Io(io::Error),
+//! }
+//!
+//! fn foo(...) -> Result<..., Error![error::NotFound, error::InvalidRange, io::Error]> {
+//!     Err(error::InvalidRange { min: 4, max: 2 })?
+//! }
 //! ```
 //!
-//! Each variant of the `enum` decorated by `#[powerset_enum]` must be a tuple-struct variant with
-//! a single item, and the type of that item must be unique within that `enum`.  Parametrization of
-//! the `enum` (beside the one created by `#[powerset_enum]`) is not supported.
+//! The module named after the `enum` is only generated when the `enum` has variants like these or
+//! variants that share their item type, so an `enum` without them can live next to a module with
+//! that name. `#[powerset_enum(module = "name")]` gives the module another name, and generates it
+//! for any `enum`.
 //!
 //! To use a specific parametrization, use a macro with the same name of the enum and provide to it
-//! the list of types you require.
+//! the list of types you require. Variants can also be listed by their names instead of their
+//! types, so `Error![NotFound, InvalidRange, Io]` is the same subset as
+//! `Error![error::NotFound, error::InvalidRange, io::Error]` in the example above. A name is
+//! resolved through the `enum` itself, so the variant's item type does not need to be nameable
+//! where the macro is used.
+//!
+//! The macro expands to the `enum` with concrete generic arguments - `Error![Io, NotFound]` is
+//! `Error<error::NotFound, Never, io::Error>` - as long as the types listed are spelled the way
+//! they are in the `enum`'s declaration. Types spelled differently (e.g. through an alias) are
+//! still accepted, but are added through [WithVariant] projections.
+//!
+//! The `enum` may have generic parameters of its own - lifetimes included. They are kept in front
+//! of the parameters created by `#[powerset_enum]`, and the macro accepts their arguments before a
+//! `;`:
+//!
+//! ```ignore
+//! #[powerset_enum]
+//! enum Error<'a, B> {
+//!     Backend(B),
+//!     Parse(ParseError<'a>),
+//!     Io(io::Error),
+//! }
+//!
+//! fn foo<'a, B>(...) -> Result<..., Error!['a, B; ParseError<'a>, io::Error]> {
+//!     ...
+//! }
+//! ```
+//!
+//! A variant whose item is one of these parameters (like `Backend` above) does not get a
+//! parameter created for it - its presence in the subset is controlled by the argument passed to
+//! the `enum`'s parameter, with [Never] excluding it. Such variants are not addressed by type, so
+//! they don't get `From`, `WithVariant` or `WithoutVariant` implementations.
+//!
+//! Subsets can be combined with the [Union], [Intersection] and [Difference] aliases. The macro
+//! also accepts whole subsets, prefixed with `..`, and adds all their variants:
+//!
+//! ```ignore
+//! type ReadErrors = Error![Io, NotFound];
+//! type ParseErrors = Error![InvalidRange];
+//!
+//! fn foo(...) -> Result<..., Error![..ReadErrors, ..ParseErrors]> {
+//!     ...
+//! }
+//! ```
 //!
 //! An `upcast` method is created on the `enum` type to convert any subset to any superset of that
 //! subsets. Usually used with [Result::map_err].
@@ -27,6 +111,165 @@
 //! }
 //! ```
 //!
+//! The same conversion is available through the [Upcast] `trait`. With the
+//! [`#[auto_upcast]`](../powerset_enum_attr/attr.auto_upcast.html) attribute, `?` in a function
+//! upcasts subsets into the function's error by itself, and converts other errors - including
+//! subsets that cannot be upcast into it, like those of other `enum`s - with [From] as usual. The
+//! function's return type must be a [Result], or an alias of one. There, `?` can only be applied
+//! to a [Result], and `.map_err(E::upcast)` before it becomes ambiguous, so it should be dropped.
+//! `?` in closures, `async` blocks and nested items is left as is:
+//!
+//! ```ignore
+//! #[auto_upcast]
+//! fn bar(...) -> Result<..., E![A, B, C, D]> {
+//!     let value = foo(...)?;
+//!     ...
+//! }
+//! ```
+//!
+//! The [`#[throws]`](../powerset_enum_attr/attr.throws.html) attribute writes the [Result] in a
+//! function's signature. It takes the `enum`'s macro, followed by a `:` and whatever that macro
+//! would take, and wraps the function's tail expression in `Ok`. `?` in the function upcasts
+//! subsets like it does with `#[auto_upcast]`. Early `return`s still need to return a [Result]:
+//!
+//! ```ignore
+//! #[throws(E: A, B)]
+//! fn foo(...) -> Data { // returns `Result<Data, E![A, B]>`
+//!     if ... {
+//!         return Err(A.into());
+//!     }
+//!     data
+//! }
+//! ```
+//!
+//! The macro is only usable in the crate that defines the `enum`. To make it usable from other
+//! crates, use `#[powerset_enum(export)]` for an `enum` in the crate root, or
+//! `#[powerset_enum(export = "path::to::module")]` for an `enum` elsewhere, where the path is of
+//! the `enum`'s module from the crate root. The macro is then exported from the crate root like
+//! any `#[macro_export]` macro, and the `enum`'s module must be reachable from other crates:
+//!
+//! ```ignore
+//! // In `mylib/src/errors.rs`:
+//! #[powerset_enum(export = "errors")]
+//! pub enum Error {
+//!     Io(io::Error),
+//!     Json(serde_json::Error),
+//! }
+//!
+//! // In a crate that depends on `mylib` - but not necessarily on `powerset_enum`:
+//! fn foo(...) -> Result<..., mylib::Error![Io, Json]> {
+//!     ...
+//! }
+//! ```
+//!
+//! The generated code refers to this crate as `::powerset_enum`. When it is renamed in
+//! `Cargo.toml` or used through a re-export, pass its path with
+//! `#[powerset_enum(crate = "path::to::powerset_enum")]`. `#[auto_upcast]` and `#[throws]` take
+//! it the same way, before their other arguments.
+//!
+//! With `#[powerset_enum(error)]`, `Display` and `std::error::Error` are implemented for every
+//! subset, delegating both the message and the `source` to the variant's item. Every subset of
+//! such an `enum` can be converted with `?` into a `Box<dyn std::error::Error>`. The `enum` must
+//! implement `Debug`. The types generated for unit, struct-like and multi-item variants implement
+//! `std::error::Error` too, and are displayed the same way they are debug-formatted.
+//!
+//! Every subset has `variant_name`, `variant_index` and `payload_type_id` methods for finding out
+//! which variant it holds without matching on it, and a `VARIANT_NAMES` const with the names of
+//! the variants that are possible in it. The index is the variant's position in the `enum`'s
+//! declaration, and variants holding the `enum`'s own type parameters are always listed as
+//! possible:
+//!
+//! ```ignore
+//! log::error!("request failed with {}", error.variant_name());
+//! assert_eq!(<Error![Io, NotFound]>::VARIANT_NAMES, ["NotFound", "Io"]);
+//! ```
+//!
+//! Every subset also implements the [Powerset] `trait`, for code that is generic over them. It
+//! provides the variant names and count, and the `Full` and `Empty` subsets of the `enum`:
+//!
+//! ```ignore
+//! fn log_failure<E: Powerset + Debug>(error: &E) {
+//!     log::error!("{:?} - one of {} possible errors", error, E::VARIANT_COUNT);
+//! }
+//! ```
+//!
+//! Variants can be given stable codes with `#[powerset(code = ...)]` - integers, which are
+//! `u32`s, or strings. Once one variant has a code all of them need one, and no two can share it.
+//! The codes add a `code` method, a `CODES` const with the codes of the variants that are possible
+//! in the subset, and a `from_code` method that builds a variant from its payload's `Default` -
+//! for subsets where all the payloads implement it:
+//!
+//! ```ignore
+//! #[powerset_enum]
+//! enum ApiError {
+//!     #[powerset(code = 1001)]
+//!     NotFound,
+//!     #[powerset(code = 1004)]
+//!     RateLimited,
+//! }
+//!
+//! assert_eq!(<ApiError![NotFound, RateLimited]>::CODES, [1001, 1004]);
+//! let error = <ApiError![NotFound, RateLimited]>::from_code(1004).unwrap();
+//! assert_eq!(error.code(), 1004);
+//! ```
+//!
+//! With the `http` feature, variants can be given HTTP statuses with
+//! `#[powerset(status = ...)]`, and the `status` method returns the status of the variant as an
+//! `http::StatusCode` - `500 Internal Server Error` for variants that were not given one. With the
+//! `axum` feature, `#[powerset_enum(axum)]` implements axum's `IntoResponse` for every subset that
+//! implements `Display`, responding with the status and the error's message, so handlers can
+//! return subsets as their errors. Server errors respond with the status' canonical reason
+//! instead, so their messages don't reveal internals:
+//!
+//! ```ignore
+//! #[powerset_enum(error, axum)]
+//! #[derive(Debug)]
+//! enum ApiError {
+//!     #[powerset(status = 404)]
+//!     NotFound,
+//!     #[powerset(status = 401)]
+//!     Unauthorized,
+//!     Db(DbError),
+//! }
+//!
+//! async fn get_user(...) -> Result<Json<User>, ApiError![NotFound, Unauthorized, Db]> {
+//!     ...
+//! }
+//! ```
+//!
+//! Variants can be given the status the process exits with when `main` fails with them, with
+//! `#[powerset(exit_code = ...)]`. The `exit_code` method returns it - 1 for variants that were
+//! not given one - and every subset that implements `Display` implements
+//! `std::process::Termination`, printing its message to stderr. `main` returns the subset wrapped
+//! in [Exit], because a [Result] would be reported with `Debug` and exit with 1:
+//!
+//! ```ignore
+//! fn main() -> Exit<CliError![Config, Io, Usage]> {
+//!     run().into()
+//! }
+//! ```
+//!
+//! With the `serde` feature, `#[powerset_enum(serde)]` implements `Serialize` and `Deserialize`
+//! for every subset. They are tagged by the variant's name, the way `serde` does for `enum`s by
+//! default, and deserializing a variant that is not in the subset fails with an unknown variant
+//! error. `Serialize` and `Deserialize` in the `enum`'s `derive` are only applied to the types
+//! generated for its variants. Variants holding the `enum`'s own type parameters need them to
+//! implement `Serialize` and `Deserialize` - which [Never] does not.
+//!
+//! When every payload is an error, `try_from_dyn` recovers a subset from a type-erased error by
+//! downcasting it to the payload of each variant in declaration order, giving the error back when
+//! none fits, and `into_dyn` erases a subset into one. Both work with
+//! `Box<dyn Error + Send + Sync>`, and with `anyhow::Error` and `eyre::Report` under the `anyhow`
+//! and `eyre` features - see [DynError]:
+//!
+//! ```ignore
+//! let error: anyhow::Error = ...;
+//! match <E![A, B]>::try_from_dyn(error) {
+//!     Ok(e) => ...,
+//!     Err(error) => ...,
+//! }
+//! ```
+//!
 //! The [Extract] `trait` provides an `extract` method on the `enum` type and on [Result] with the
 //! `enum` as their error to extract a new [Result] where the OK value is the original value
 //! without the extracted variant and the error is the extracted variant:
@@ -37,8 +280,61 @@
 //! }
 //! ```
 //!
+//! The [Handle] `trait` provides a `handle` method on [Result] that recovers from a single variant
+//! of the error using a closure, removing it from the possible errors, and a `finish` method that
+//! only compiles once all the variants were handled:
+//!
+//! ```ignore
+//! fn corge(...) -> ... {
+//!     bar(...)
+//!         .handle(|a: A| ...)
+//!         .handle(|b: B| ...)
+//!         .handle(|c: C| ...)
+//!         .handle(|d: D| ...)
+//!         .finish()
+//! }
+//! ```
+//!
+//! The [match_powerset] macro matches on a subset without relying on `exhaustive_patterns`,
+//! which is needed on stable Rust to leave out the arms of the absent variants. It fails to
+//! compile when a variant of the subset has no arm, naming the payload type of that variant:
+//!
+//! ```ignore
+//! fn grault(e: E![A, B]) -> ... {
+//!     match_powerset!(e {
+//!         A(a) => ...,
+//!         b: B => ...,
+//!     })
+//! }
+//! ```
+//!
+//! A `try_narrow` method is created on the `enum` type to narrow a subset down to a smaller
+//! subset. It returns the narrowed value, or the variants that don't fit in it as the complement
+//! subset:
+//!
+//! ```ignore
+//! fn qux(...) -> Result<..., E![A, B]> {
+//!     bar(...).or_else(|e| match e.try_narrow::<E![A, B]>() {
+//!         Ok(e) => Err(e),
+//!         Err(E::C(_)) => Ok(...),
+//!         Err(E::D(_)) => Ok(...),
+//!     })
+//! }
+//! ```
+//!
+//! The [Split] `trait` provides the same on [Result] with the `enum` as its error, as a `split`
+//! method. It splits off the variants of a subset, leaving the rest in the original [Result]:
+//!
+//! ```ignore
+//! fn quux(...) -> Result<..., E![C, D]> {
+//!     match bar(...).split::<E![A, B]>() {
+//!         Ok(retryable) => retry(retryable),
+//!         Err(rest) => rest,
+//!     }
+//! }
+//! ```
+//!
 //! ```
-//! #![feature(never_type, exhaustive_patterns, proc_macro_hygiene)]
 //! # use powerset_enum::*;
 //!
 //! # #[derive(Debug, PartialEq)]
@@ -94,5 +390,29 @@
 //! }
 //! ```
 
-pub use powerset_enum_attr::{powerset_enum, powerset};
+pub use powerset_enum_attr::{powerset_enum, powerset, throws, auto_upcast};
+#[doc(hidden)]
+pub use powerset_enum_attr::match_powerset as __match_powerset;
 pub use powerset_enum_traits::*;
+
+/// Match on a subset with an arm for each of its variants, checking at compile time that none
+/// was left out - without relying on `exhaustive_patterns`.
+///
+/// Each arm is a pattern of a variant's payload or tag, named after its type, `binding: Type` for
+/// payloads that cannot be destructured this way, or `_` as the last arm for all the variants
+/// that were not handled by the other arms. Generic types need their arguments, so their patterns
+/// are written with a turbofish - e.g. `Wrapped::<u32>(inner)`:
+///
+/// ```ignore
+/// match_powerset!(error {
+///     Exception1 => 1,
+///     Wrapped(inner) => inner.len(),
+///     error: io::Error => error.raw_os_error().unwrap_or(0),
+/// })
+/// ```
+#[macro_export]
+macro_rules! match_powerset {
+    ($($tokens:tt)*) => {
+        $crate::__match_powerset!($crate; $($tokens)*)
+    };
+}
@@ -0,0 +1,7 @@
+// The expected messages name the stable `Never`.
+#[cfg(not(feature = "nightly"))]
+#[test]
+fn test_compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
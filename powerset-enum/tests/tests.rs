@@ -1,4 +1,4 @@
-#![feature(never_type, exhaustive_patterns, proc_macro_hygiene)]
+#![allow(clippy::type_complexity, clippy::needless_question_mark)]
 use powerset_enum::*;
 
 #[derive(Debug, PartialEq)]
@@ -49,3 +49,799 @@ fn test_extract_variant() {
     assert!(cause_error(4).extract::<Exception2>() == Ok(Err(Error::Exception4(Exception4))));
     assert!(cause_error(6).extract::<Exception2>() == Ok(Ok(6)));
 }
+
+#[test]
+fn test_adding_never_does_nothing() {
+    let error: Error![Exception1, Exception2] = Exception1.into();
+    let error: Error![Exception1, Exception2] = WithVariant::<Never>::add_possibility(error);
+    assert!(error == Error::Exception1(Exception1));
+}
+
+#[derive(Debug, PartialEq)]
+struct Wrapped<T>(T);
+
+#[powerset_enum]
+#[derive(Debug, PartialEq)]
+enum GenericError<B: std::fmt::Debug>
+where
+    B: PartialEq,
+{
+    Backend(B),
+    Wrapped(Wrapped<B>),
+    Exception1(Exception1),
+}
+
+#[test]
+fn test_generic_parameters() {
+    fn foo<B: std::fmt::Debug + PartialEq>(backend: Option<B>) -> Result<(), GenericError![B; Exception1]> {
+        if let Some(backend) = backend {
+            return Err(GenericError::Backend(backend));
+        }
+        Err(Exception1)?
+    }
+
+    fn bar<B: std::fmt::Debug + PartialEq>(backend: Option<B>) -> Result<(), GenericError![B; Wrapped<B>, Exception1]> {
+        foo(backend).map_err(GenericError::upcast)
+    }
+
+    assert!(bar(Some(42)) == Err(GenericError::Backend(42)));
+    assert!(bar::<Never>(None) == Err(GenericError::Exception1(Exception1)));
+    assert!(bar::<usize>(None).extract::<Exception1>() == Err(Exception1));
+
+    let error: GenericError![&str; Wrapped<&str>] = Wrapped("wrapped").into();
+    assert!(error.extract::<Wrapped<&str>>() == Err(Wrapped("wrapped")));
+}
+
+#[derive(Debug, PartialEq)]
+struct ParseError<'a> {
+    unparsed: &'a str,
+}
+
+#[powerset_enum]
+#[derive(Debug, PartialEq)]
+enum BorrowingError<'a> {
+    Parse(ParseError<'a>),
+    Utf8(std::str::Utf8Error),
+}
+
+#[test]
+fn test_lifetime_parameters() {
+    fn parse(input: &str) -> Result<usize, BorrowingError!['_; ParseError<'_>]> {
+        input.trim().parse().map_err(|_| ParseError { unparsed: input }.into())
+    }
+
+    fn parse_bytes<'a>(input: &'a [u8]) -> Result<usize, BorrowingError!['a; ParseError<'a>, std::str::Utf8Error]> {
+        let input = std::str::from_utf8(input)?;
+        parse(input).map_err(BorrowingError::upcast)
+    }
+
+    assert!(parse_bytes(b" 42 ") == Ok(42));
+    assert!(parse_bytes(b"x").extract::<ParseError>() == Err(ParseError { unparsed: "x" }));
+    assert!(parse_bytes(b"\xff").extract::<ParseError>().unwrap().is_err());
+}
+
+#[powerset_enum]
+#[derive(Debug, PartialEq)]
+enum LookupError {
+    NotFound,
+    Exception1(Exception1),
+}
+
+#[test]
+fn test_unit_variants() {
+    fn lookup(key: usize) -> Result<usize, LookupError![lookup_error::NotFound]> {
+        if key == 0 {
+            Err(lookup_error::NotFound)?;
+        }
+        Ok(key)
+    }
+
+    fn lookup_positive(key: usize) -> Result<usize, LookupError![lookup_error::NotFound, Exception1]> {
+        if key == 1 {
+            Err(Exception1)?;
+        }
+        lookup(key).map_err(LookupError::upcast)
+    }
+
+    assert!(lookup_positive(0) == Err(LookupError::NotFound(lookup_error::NotFound)));
+    assert!(lookup_positive(0).extract::<lookup_error::NotFound>() == Err(lookup_error::NotFound));
+    assert!(lookup_positive(1).extract::<lookup_error::NotFound>() == Ok(Err(Exception1.into())));
+    assert!(lookup_positive(2).extract::<lookup_error::NotFound>() == Ok(Ok(2)));
+}
+
+#[powerset_enum]
+#[derive(Debug, PartialEq)]
+enum RangeError<'a> {
+    InvalidRange { min: u32, max: u32 },
+    OutOfRange(u32, ParseError<'a>),
+    Exception1(Exception1),
+}
+
+#[test]
+fn test_variants_with_generated_carriers() {
+    fn check_range(min: u32, max: u32) -> Result<(), RangeError!['static; range_error::InvalidRange]> {
+        if max < min {
+            Err(range_error::InvalidRange { min, max })?;
+        }
+        Ok(())
+    }
+
+    fn check_value<'a>(value: &'a str, min: u32, max: u32) -> Result<u32, RangeError!['a; range_error::InvalidRange, range_error::OutOfRange<'a>]> {
+        check_range(min, max).map_err(RangeError::upcast)?;
+        let parsed = value.len() as u32;
+        if parsed < min || max < parsed {
+            Err(range_error::OutOfRange(parsed, ParseError { unparsed: value }))?;
+        }
+        Ok(parsed)
+    }
+
+    assert!(check_value("abc", 4, 2) == Err(RangeError::InvalidRange(range_error::InvalidRange { min: 4, max: 2 })));
+    assert!(check_value("abc", 1, 2).extract::<range_error::InvalidRange>() == Ok(Err(RangeError::OutOfRange(range_error::OutOfRange(3, ParseError { unparsed: "abc" })))));
+    assert!(check_value("abc", 1, 3) == Ok(3));
+}
+
+#[powerset_enum]
+#[derive(Debug, PartialEq)]
+enum TransferError {
+    ReadFailed(Exception1),
+    WriteFailed(Exception1),
+    Exception2(Exception2),
+}
+
+#[test]
+fn test_variants_sharing_a_payload_type() {
+    use transfer_error::tags::{ReadFailed, WriteFailed};
+
+    fn transfer(n: usize) -> Result<(), TransferError![ReadFailed, WriteFailed]> {
+        match n {
+            1 => Err(ReadFailed(Exception1))?,
+            2 => Err(WriteFailed(Exception1))?,
+            _ => Ok(()),
+        }
+    }
+
+    fn transfer_all(n: usize) -> Result<(), TransferError![ReadFailed, WriteFailed, Exception2]> {
+        transfer(n).map_err(TransferError::upcast)?;
+        Err(Exception2)?
+    }
+
+    assert!(transfer(1) == Err(TransferError::ReadFailed(Exception1)));
+    assert!(transfer(2).extract::<ReadFailed>() == Ok(Err(TransferError::WriteFailed(Exception1))));
+    assert!(transfer(1).extract::<ReadFailed>() == Err(ReadFailed(Exception1)));
+    assert!(transfer_all(2) == Err(TransferError::WriteFailed(Exception1)));
+    assert!(transfer_all(3) == Err(TransferError::Exception2(Exception2)));
+}
+
+#[test]
+fn test_addressing_variants_by_name() {
+    fn parse<'a>(input: &'a str) -> Result<usize, BorrowingError!['a; Parse]> {
+        input.trim().parse().map_err(|_| ParseError { unparsed: input }.into())
+    }
+
+    fn check_value(value: &str) -> Result<u32, RangeError!['_; InvalidRange, OutOfRange, Exception1]> {
+        let parsed = value.len() as u32;
+        if parsed == 0 {
+            Err(Exception1)?;
+        }
+        Err(range_error::OutOfRange(parsed, ParseError { unparsed: value }))?
+    }
+
+    fn transfer() -> Result<(), TransferError![WriteFailed, Exception2]> {
+        Err(transfer_error::tags::WriteFailed(Exception1))?
+    }
+
+    let error: GenericError![&str; Wrapped] = Wrapped("wrapped").into();
+    assert!(error == GenericError::Wrapped(Wrapped("wrapped")));
+    assert!(parse("x") == Err(BorrowingError::Parse(ParseError { unparsed: "x" })));
+    assert!(check_value("").extract::<Exception1>() == Err(Exception1));
+    assert!(transfer() == Err(TransferError::WriteFailed(Exception1)));
+}
+
+trait Describe {
+    fn describe() -> &'static str;
+}
+
+// A type parameter is only constrained by an implementation on a concrete type - not by one on
+// an associated type projection.
+impl<B: std::fmt::Debug + PartialEq> Describe for GenericError![B; Wrapped<B>, Exception1] {
+    fn describe() -> &'static str {
+        "wrapped and exception 1"
+    }
+}
+
+impl<'a> Describe for RangeError!['a; OutOfRange, range_error::tags::InvalidRange] {
+    fn describe() -> &'static str {
+        "out of range and invalid range"
+    }
+}
+
+#[test]
+fn test_subset_macro_expands_to_concrete_types() {
+    assert_eq!(<GenericError<usize, Wrapped<usize>, Exception1>>::describe(), "wrapped and exception 1");
+    assert_eq!(<GenericError![Never; Exception1, Never, Wrapped<Never>]>::describe(), "wrapped and exception 1");
+    assert_eq!(<RangeError<'static, range_error::InvalidRange, range_error::OutOfRange<'static>, Never>>::describe(), "out of range and invalid range");
+}
+
+#[derive(Debug, PartialEq)]
+struct ConnectionLost;
+
+impl std::fmt::Display for ConnectionLost {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "connection lost")
+    }
+}
+
+impl std::error::Error for ConnectionLost {}
+
+#[derive(Debug)]
+struct RequestFailed(ConnectionLost);
+
+impl std::fmt::Display for RequestFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "request failed")
+    }
+}
+
+impl std::error::Error for RequestFailed {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[powerset_enum(error)]
+#[derive(Debug)]
+enum ClientError<B> {
+    Backend(B),
+    RequestFailed(RequestFailed),
+    Timeout { seconds: u32 },
+    Cancelled,
+}
+
+#[test]
+fn test_generated_error_trait_impls() {
+    fn request(n: usize) -> Result<(), ClientError![Never; RequestFailed, Timeout, Cancelled]> {
+        match n {
+            1 => Err(RequestFailed(ConnectionLost))?,
+            2 => Err(client_error::Timeout { seconds: 30 })?,
+            3 => Err(client_error::Cancelled)?,
+            _ => Ok(()),
+        }
+    }
+
+    fn boxed(n: usize) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(request(n)?)
+    }
+
+    let error = boxed(1).unwrap_err();
+    assert_eq!(error.to_string(), "request failed");
+    assert_eq!(error.source().unwrap().to_string(), "connection lost");
+    assert_eq!(boxed(2).unwrap_err().to_string(), "Timeout { seconds: 30 }");
+    assert!(boxed(3).unwrap_err().source().is_none());
+
+    let error: ClientError![std::fmt::Error; Cancelled] = ClientError::Backend(std::fmt::Error);
+    assert_eq!(error.to_string(), std::fmt::Error.to_string());
+}
+
+// These have the names the generated generic parameters would have had.
+#[derive(Debug, PartialEq)]
+struct T0;
+#[derive(Debug, PartialEq)]
+struct O1;
+#[derive(Debug, PartialEq)]
+struct N2;
+#[derive(Debug, PartialEq)]
+#[allow(non_camel_case_types)]
+struct T_0;
+
+#[powerset_enum]
+#[derive(Debug, PartialEq)]
+enum CollidingError<T1: std::fmt::Debug> {
+    T0(T0),
+    O1(O1),
+    N2(N2),
+    Underscored(T_0),
+    Generic(Wrapped<T1>),
+}
+
+#[test]
+fn test_generated_generic_parameters_do_not_collide() {
+    fn foo(n: usize) -> Result<(), CollidingError![Never; T0, N2]> {
+        match n {
+            0 => Err(T0)?,
+            _ => Err(N2)?,
+        }
+    }
+
+    fn bar(n: usize) -> Result<(), CollidingError![Never; T0, O1, N2, T_0]> {
+        foo(n).map_err(CollidingError::upcast)?;
+        Err(O1)?
+    }
+
+    assert!(bar(0) == Err(CollidingError::T0(T0)));
+    assert!(bar(1).extract::<N2>() == Err(N2));
+    let error: CollidingError![usize; Generic, Underscored] = Wrapped(1).into();
+    assert!(error.extract::<T_0>() == Ok(CollidingError::Generic(Wrapped(1))));
+}
+
+#[test]
+fn test_try_narrow() {
+    fn narrow(error: Error![Exception1, Exception2, Exception3, Exception4]) -> Result<Error![Exception1, Exception3], Error![Exception2, Exception4]> {
+        error.try_narrow()
+    }
+
+    assert!(narrow(Exception3.into()) == Ok(Error::Exception3(Exception3)));
+    assert!(narrow(Exception4.into()) == Err(Error::Exception4(Exception4)));
+
+    let error: Error![Exception1, Exception2] = Exception2.into();
+    assert!(error.try_narrow::<Error![Exception2, Exception3]>() == Ok(Error::Exception2(Exception2)));
+
+    let error: GenericError![usize; Wrapped, Exception1] = GenericError::Backend(42);
+    assert!(error.try_narrow::<GenericError![usize; Exception1]>() == Ok(GenericError::Backend(42)));
+    let error: GenericError![usize; Wrapped, Exception1] = Wrapped(42).into();
+    let rest: GenericError![usize; Wrapped] = error.try_narrow::<GenericError![usize; Exception1]>().unwrap_err();
+    assert!(rest == GenericError::Wrapped(Wrapped(42)));
+}
+
+#[test]
+fn test_split() {
+    fn retry_or_propagate(n: usize) -> Result<usize, Error![Exception3, Exception4]> {
+        let retryable = match cause_error(n).split::<Error![Exception1, Exception2]>() {
+            Ok(retryable) => retryable,
+            Err(rest) => return rest,
+        };
+        match retryable {
+            Error::Exception1(_) => Ok(10),
+            Error::Exception2(_) => Ok(20),
+        }
+    }
+
+    assert!(retry_or_propagate(0) == Ok(0));
+    assert!(retry_or_propagate(1) == Ok(10));
+    assert!(retry_or_propagate(2) == Ok(20));
+    assert!(retry_or_propagate(3) == Err(Error::Exception3(Exception3)));
+
+    let error: Error![Exception1, Exception4] = Exception4.into();
+    assert!(error.split::<Error![Exception4]>() == Ok(Error::Exception4(Exception4)));
+}
+
+type ParseErrors = Error![Exception1, Exception2];
+type FetchErrors = Error![Exception2, Exception3];
+
+#[test]
+fn test_set_operations() {
+    fn parse(n: usize) -> Result<usize, ParseErrors> {
+        match n {
+            1 => Err(Exception1)?,
+            2 => Err(Exception2)?,
+            n => Ok(n),
+        }
+    }
+
+    fn fetch(n: usize) -> Result<usize, FetchErrors> {
+        match n {
+            3 => Err(Exception3)?,
+            n => Ok(n),
+        }
+    }
+
+    fn parse_and_fetch(n: usize) -> Result<usize, Error![..ParseErrors, ..FetchErrors, Exception4]> {
+        if n == 4 {
+            Err(Exception4)?;
+        }
+        let n = parse(n).map_err(Error::upcast)?;
+        fetch(n).map_err(Error::upcast)
+    }
+
+    assert!(parse_and_fetch(1) == Err(Error::Exception1(Exception1)));
+    assert!(parse_and_fetch(3) == Err(Error::Exception3(Exception3)));
+    assert!(parse_and_fetch(4) == Err(Error::Exception4(Exception4)));
+
+    let union: Union<ParseErrors, FetchErrors> = Exception3.into();
+    let _: Error![Exception1, Exception2, Exception3] = union;
+    let intersection: Intersection<ParseErrors, FetchErrors> = Exception2.into();
+    let _: Error![Exception2] = intersection;
+    let difference: Difference<ParseErrors, FetchErrors> = Exception1.into();
+    let _: Error![Exception1] = difference;
+}
+
+#[test]
+fn test_handle() {
+    fn handle_all(n: usize) -> usize {
+        cause_error(n)
+            .handle(|_: Exception1| 10)
+            .handle(|_: Exception3| 30)
+            .handle(|_: Exception2| 20)
+            .handle(|_: Exception4| 40)
+            .finish()
+    }
+
+    assert_eq!(handle_all(0), 0);
+    assert_eq!(handle_all(1), 10);
+    assert_eq!(handle_all(2), 20);
+    assert_eq!(handle_all(4), 40);
+
+    let result: Result<usize, GenericError![Never; Wrapped<Never>, Exception1]> = Err(Exception1.into());
+    assert_eq!(result.handle(|_: Exception1| 1).handle(|_: Wrapped<Never>| 2).finish(), 1);
+
+    let result: Result<usize, BorrowingError!['static; Parse]> = Ok(5);
+    assert_eq!(result.handle(|_: ParseError| 0).finish(), 5);
+}
+
+#[test]
+fn test_throws() {
+    #[throws(Error: Exception1, Exception2)]
+    fn foo(n: usize) -> usize {
+        if n == 1 {
+            return Err(Exception1.into());
+        }
+        if n == 2 {
+            Err(Exception2)?;
+        }
+        n
+    }
+
+    #[throws(Error: Exception3)]
+    fn bar(n: usize) {
+        if n == 3 {
+            Err(Exception3)?;
+        }
+    }
+
+    struct Checker;
+
+    impl Checker {
+        #[throws(GenericError: Never; Wrapped<Never>, Exception1)]
+        fn check(&self) -> &'static str {
+            Err(Exception1)?;
+            "unreachable"
+        }
+    }
+
+    let _: fn(usize) -> Result<usize, Error![Exception1, Exception2]> = foo;
+    assert_eq!(foo(0), Ok(0));
+    assert_eq!(foo(1), Err(Exception1.into()));
+    assert_eq!(foo(2), Err(Exception2.into()));
+    assert_eq!(bar(0), Ok(()));
+    assert_eq!(bar(3), Err(Exception3.into()));
+    assert_eq!(Checker.check(), Err(Exception1.into()));
+}
+
+#[test]
+fn test_auto_upcast() {
+    #[auto_upcast]
+    fn foo(n: usize) -> Result<usize, Error![Exception1, Exception2, Exception3]> {
+        let value = cause_error(n).extract::<Exception4>().unwrap_or(Ok(4))?;
+        // Closures are left as is, so `?` still works on `Option` in them.
+        let doubled = (|| Some(Some(value)? * 2))().unwrap();
+        Ok(doubled)
+    }
+
+    #[throws(Error: Exception1, Exception2, Exception3, Exception4)]
+    fn bar(n: usize) -> usize {
+        foo(n)? + 1
+    }
+
+    #[auto_upcast]
+    fn generic<E: Into<Error![Exception1, Exception2]>>(result: Result<usize, E>) -> Result<usize, Error![Exception1, Exception2]> {
+        Ok(result.map_err(Into::into)?)
+    }
+
+    assert_eq!(bar(0), Ok(1));
+    assert_eq!(bar(2), Err(Exception2.into()));
+    assert_eq!(bar(3), Err(Exception3.into()));
+    assert_eq!(bar(4), Ok(9));
+    assert_eq!(generic(Err(Exception1)), Err(Exception1.into()));
+}
+
+#[test]
+fn test_match_powerset() {
+    fn describe(n: usize) -> &'static str {
+        match cause_error(n) {
+            Ok(_) => "ok",
+            Err(error) => match_powerset!(error {
+                Exception1 => "1",
+                Exception2 => "2",
+                error: Exception3 => {
+                    assert_eq!(error, Exception3);
+                    "3"
+                }
+                _: Exception4 => "4",
+            }),
+        }
+    }
+
+    assert_eq!(describe(0), "ok");
+    assert_eq!(describe(1), "1");
+    assert_eq!(describe(3), "3");
+    assert_eq!(describe(4), "4");
+
+    let error: GenericError![&str; Wrapped<&str>, Exception1] = Wrapped("wrapped").into();
+    assert_eq!(match_powerset!(error { Wrapped::<&str>(inner) => inner, _ => "rest" }), "wrapped");
+
+    let error: Error![Exception2] = Exception2.into();
+    assert_eq!(match_powerset!(error { Exception2 => 2 }), 2);
+}
+
+#[test]
+fn test_introspection() {
+    use std::any::TypeId;
+
+    let error: Error![Exception2, Exception4] = Exception4.into();
+    assert_eq!(error.variant_name(), "Exception4");
+    assert_eq!(error.variant_index(), 3);
+    assert_eq!(error.payload_type_id(), TypeId::of::<Exception4>());
+    assert_eq!(<Error![Exception2, Exception4]>::VARIANT_NAMES, ["Exception2", "Exception4"]);
+    assert_eq!(<Error![]>::VARIANT_NAMES, [] as [&str; 0]);
+
+    let error: GenericError![u32; Exception1] = GenericError::Backend(3);
+    assert_eq!(error.variant_name(), "Backend");
+    assert_eq!(error.variant_index(), 0);
+    assert_eq!(error.payload_type_id(), TypeId::of::<u32>());
+    assert_eq!(<GenericError![u32; Exception1]>::VARIANT_NAMES, ["Backend", "Exception1"]);
+}
+
+#[derive(Debug, PartialEq)]
+struct DecodeError;
+#[derive(Debug, PartialEq)]
+struct ConnectionError;
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "decode error")
+    }
+}
+impl std::error::Error for DecodeError {}
+
+impl std::fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "connection error")
+    }
+}
+impl std::error::Error for ConnectionError {}
+
+#[powerset_enum]
+#[derive(Debug, PartialEq)]
+enum TransportError {
+    DecodeError(DecodeError),
+    ConnectionError(ConnectionError),
+}
+
+#[test]
+fn test_dyn_error() {
+    let error: TransportError![DecodeError, ConnectionError] = ConnectionError.into();
+    let boxed = error.into_boxed_dyn();
+    assert_eq!(boxed.to_string(), "connection error");
+    assert_eq!(
+        <TransportError![DecodeError, ConnectionError]>::try_from_dyn(boxed).unwrap(),
+        TransportError::ConnectionError(ConnectionError),
+    );
+
+    let boxed: Box<dyn std::error::Error + Send + Sync> = Box::new(ConnectionError);
+    let boxed = <TransportError![DecodeError]>::try_from_dyn(boxed).unwrap_err();
+    assert!(boxed.is::<ConnectionError>());
+
+    let boxed: Box<dyn std::error::Error + Send + Sync> = "not a payload".into();
+    assert!(<TransportError![DecodeError, ConnectionError]>::try_from_dyn(boxed).is_err());
+}
+
+#[derive(Debug, PartialEq, Default)]
+struct RateLimited;
+#[derive(Debug, PartialEq)]
+struct Unauthorized {
+    user: String,
+}
+
+#[powerset_enum]
+#[derive(Debug, PartialEq)]
+enum ApiError {
+    #[powerset(code = 1001)]
+    NotFound,
+    #[powerset(code = 1004)]
+    RateLimited(RateLimited),
+    #[powerset(code = 1007)]
+    Unauthorized(Unauthorized),
+}
+
+#[powerset_enum]
+#[derive(Debug, PartialEq)]
+enum NamedCodeError<B> {
+    #[powerset(code = "E_BACKEND")]
+    Backend(B),
+    #[powerset(code = "E_TIMEOUT")]
+    Timeout,
+}
+
+#[test]
+fn test_codes() {
+    let error: ApiError![NotFound, Unauthorized] = Unauthorized { user: "bob".to_owned() }.into();
+    assert_eq!(error.code(), 1007);
+    assert_eq!(<ApiError![NotFound, Unauthorized]>::CODES, [1001, 1007]);
+    assert_eq!(<ApiError![]>::CODES, [] as [u32; 0]);
+
+    type Rebuildable = ApiError![NotFound, RateLimited];
+    assert_eq!(Rebuildable::from_code(1004), Some(ApiError::RateLimited(RateLimited)));
+    assert_eq!(Rebuildable::from_code(1001).map(|error| error.code()), Some(1001));
+    assert_eq!(Rebuildable::from_code(1007), None);
+    assert_eq!(Rebuildable::from_code(42), None);
+
+    let error: NamedCodeError![u32; Timeout] = NamedCodeError::Backend(3);
+    assert_eq!(error.code(), "E_BACKEND");
+    assert_eq!(<NamedCodeError![u32; Timeout]>::CODES, ["E_BACKEND", "E_TIMEOUT"]);
+    assert_eq!(<NamedCodeError![u32;]>::CODES, ["E_BACKEND"]);
+    assert_eq!(<NamedCodeError![u32; Timeout]>::from_code("E_BACKEND"), Some(NamedCodeError::Backend(0)));
+    assert_eq!(
+        <NamedCodeError![u32; Timeout]>::from_code("E_TIMEOUT").map(|error| error.code()),
+        Some("E_TIMEOUT"),
+    );
+    assert_eq!(<NamedCodeError![u32;]>::from_code("E_TIMEOUT"), None);
+}
+
+#[test]
+fn test_powerset_trait() {
+    fn describe<P: Powerset>(_: &P) -> (usize, &'static [&'static str], usize, usize) {
+        (P::VARIANT_COUNT, P::VARIANT_NAMES, P::Full::VARIANT_COUNT, P::Empty::VARIANT_COUNT)
+    }
+
+    let error: Error![Exception2, Exception4] = Exception2.into();
+    assert_eq!(describe(&error), (2, &["Exception2", "Exception4"][..], 4, 0));
+
+    fn same_type<T>(_: Option<T>, _: Option<T>) {}
+    same_type(None::<<Error![Exception1] as Powerset>::Full>, None::<Error![Exception1, Exception2, Exception3, Exception4]>);
+    same_type(None::<<Error![Exception1] as Powerset>::Empty>, None::<Error![]>);
+
+    let error: GenericError![u32; Exception1] = GenericError::Backend(3);
+    assert_eq!(describe(&error), (2, &["Backend", "Exception1"][..], 3, 1));
+}
+
+#[powerset_enum(module = "failures", error)]
+#[derive(Debug, PartialEq, Clone)]
+enum Failure {
+    Io(std::io::Error),
+    Num(std::num::ParseIntError),
+}
+
+#[test]
+fn test_derives_with_payloads_that_lack_them() {
+    fn parse(text: &str) -> Result<u32, Failure![Num]> {
+        Ok(text.parse::<u32>()?)
+    }
+
+    fn read(path: &str) -> Result<String, Failure![Io, Num]> {
+        Ok(std::fs::read_to_string(path)?)
+    }
+
+    let error = parse("x").unwrap_err();
+    assert_eq!(error.clone(), error);
+    let tag = failures::tags::Num("x".parse::<u32>().unwrap_err());
+    assert_eq!(tag.clone(), tag);
+    assert!(read("/nonexistent/powerset-enum").is_err());
+}
+
+impl From<Failure![Num]> for Error![Exception1] {
+    fn from(_: Failure![Num]) -> Self {
+        Exception1.into()
+    }
+}
+
+#[test]
+fn test_auto_upcast_into_other_errors() {
+    fn parse(text: &str) -> Result<u32, Failure![Num]> {
+        Ok(text.parse::<u32>()?)
+    }
+
+    #[auto_upcast]
+    fn parse_boxed(text: &str) -> Result<u32, Box<dyn std::error::Error>> {
+        Ok(parse(text)? + 1)
+    }
+
+    #[throws(Error: Exception1)]
+    fn parse_into_other_enum(text: &str) -> u32 {
+        parse(text)?
+    }
+
+    #[auto_upcast]
+    fn parse_digits(text: &str) -> Result<impl Iterator<Item = u32>, Failure![Io, Num]> {
+        let number = parse(text)?;
+        Ok(number.to_string().chars().map(|c| c.to_digit(10).unwrap()).collect::<Vec<_>>().into_iter())
+    }
+
+    assert_eq!(parse_boxed("2").unwrap(), 3);
+    assert_eq!(parse_boxed("x").unwrap_err().to_string(), "invalid digit found in string");
+    assert_eq!(parse_into_other_enum("2"), Ok(2));
+    assert_eq!(parse_into_other_enum("x"), Err(Exception1.into()));
+    assert_eq!(parse_digits("12").unwrap().collect::<Vec<_>>(), [1, 2]);
+    assert!(parse_digits("x").is_err());
+}
+
+mod with_sibling_module {
+    use powerset_enum::*;
+
+    pub mod parse_failure {
+        pub fn describe() -> &'static str {
+            "not generated"
+        }
+    }
+
+    #[powerset_enum]
+    #[derive(Debug)]
+    pub enum ParseFailure {
+        Io(std::io::Error),
+        Num(std::num::ParseIntError),
+    }
+
+    #[test]
+    fn test_sibling_module_named_like_the_enum() {
+        let error: ParseFailure![Num] = "x".parse::<u32>().unwrap_err().into();
+        assert!(matches!(error, ParseFailure::Num(_)));
+        assert_eq!(parse_failure::describe(), "not generated");
+    }
+}
+
+#[test]
+fn test_enum_in_a_function_holding_its_types() {
+    #[derive(Debug, PartialEq)]
+    struct Local(u32);
+
+    #[powerset_enum]
+    #[derive(Debug, PartialEq)]
+    enum LocalError {
+        Local(Local),
+        Exception1(Exception1),
+    }
+
+    fn fail(n: u32) -> Result<(), LocalError![Local, Exception1]> {
+        if n == 0 {
+            Err(Exception1)?
+        }
+        Err(Local(n))?
+    }
+
+    assert_eq!(fail(3), Err(LocalError::Local(Local(3))));
+    assert_eq!(fail(0).extract::<Exception1>(), Err(Exception1));
+}
+
+#[powerset_enum(error)]
+#[derive(Debug)]
+enum Nothing {}
+
+#[test]
+fn test_enum_without_variants() {
+    assert_eq!(<Nothing![]>::VARIANT_NAMES, [] as [&str; 0]);
+    let result: Result<u32, Nothing![]> = Ok(1);
+    assert_eq!(result.map_err(|error| error.variant_name()), Ok(1));
+}
+
+#[macro_use]
+mod read_failures {
+    use std::io;
+    use std::num::ParseIntError;
+
+    use powerset_enum::powerset_enum;
+
+    #[powerset_enum]
+    #[derive(Debug)]
+    pub enum ReadFailure {
+        Io(io::Error),
+        Num(ParseIntError),
+    }
+}
+
+#[test]
+fn test_variant_names_without_their_types_in_scope() {
+    use read_failures::ReadFailure;
+
+    fn parse(input: &str) -> Result<u32, ReadFailure![Num]> {
+        Ok(input.parse()?)
+    }
+
+    fn read(input: &str) -> Result<u32, ReadFailure![Io, Num]> {
+        if input.is_empty() {
+            Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?
+        }
+        parse(input).map_err(|error| error.upcast())
+    }
+
+    assert_eq!(read("12").unwrap(), 12);
+    assert_eq!(read("").unwrap_err().variant_name(), "Io");
+    assert_eq!(read("x").unwrap_err().variant_name(), "Num");
+}
@@ -0,0 +1,26 @@
+use powerset_enum::{match_powerset, powerset_enum};
+
+#[derive(Debug)]
+struct NotFound;
+
+#[derive(Debug)]
+struct Timeout;
+
+#[powerset_enum]
+#[derive(Debug)]
+enum Error {
+    NotFound(NotFound),
+    Timeout(Timeout),
+    Io(std::io::Error),
+}
+
+fn describe(error: Error![NotFound, Timeout, Io]) -> &'static str {
+    match_powerset!(error {
+        NotFound => "not found",
+        Timeout => "timeout",
+    })
+}
+
+fn main() {
+    let _ = describe(NotFound.into());
+}
@@ -0,0 +1,17 @@
+use powerset_enum::powerset_enum;
+
+#[powerset_enum]
+#[derive(Debug)]
+enum Error {
+    ReadFailed(std::io::Error),
+    WriteFailed(std::io::Error),
+}
+
+fn copy() -> Result<(), Error![ReadFailed, WriteFailed]> {
+    Err(std::io::Error::other("disk full"))?;
+    Ok(())
+}
+
+fn main() {
+    let _ = copy();
+}
@@ -0,0 +1,42 @@
+#![allow(clippy::type_complexity)]
+
+//! A CLI that fails with the error named by its argument, to check the statuses it exits with.
+use std::fmt;
+
+use powerset_enum::{powerset_enum, Exit};
+
+#[derive(Debug)]
+struct ConfigError(String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "bad config: {}", self.0)
+    }
+}
+
+#[powerset_enum(error)]
+#[derive(Debug)]
+enum CliError {
+    #[powerset(exit_code = 78)]
+    Config(ConfigError),
+    #[powerset(exit_code = 74)]
+    Io(std::io::Error),
+    #[powerset(exit_code = 2)]
+    Usage,
+    Interrupted,
+}
+
+fn run(arg: Option<&str>) -> Result<(), CliError![Config, Io, Usage, Interrupted]> {
+    match arg {
+        Some("config") => Err(ConfigError("missing key".to_owned()).into()),
+        Some("io") => Err(std::io::Error::other("disk full").into()),
+        Some("interrupted") => Err(cli_error::Interrupted.into()),
+        Some(_) => Ok(()),
+        None => Err(cli_error::Usage.into()),
+    }
+}
+
+fn main() -> Exit<CliError![Config, Io, Usage, Interrupted]> {
+    let arg = std::env::args().nth(1);
+    run(arg.as_deref()).into()
+}
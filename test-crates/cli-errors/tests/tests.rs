@@ -0,0 +1,15 @@
+use std::process::Command;
+
+fn run(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_cli-errors")).args(args).output().unwrap();
+    (output.status.code(), String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn test_exit_codes() {
+    assert_eq!(run(&["ok"]), (Some(0), "".to_owned()));
+    assert_eq!(run(&["config"]), (Some(78), "Error: bad config: missing key\n".to_owned()));
+    assert_eq!(run(&["io"]), (Some(74), "Error: disk full\n".to_owned()));
+    assert_eq!(run(&[]), (Some(2), "Error: Usage\n".to_owned()));
+    assert_eq!(run(&["interrupted"]), (Some(1), "Error: Interrupted\n".to_owned()));
+}
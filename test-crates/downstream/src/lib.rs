@@ -0,0 +1 @@
+//! Uses the subset macros exported by `exported-errors` - without depending on `powerset-enum`.
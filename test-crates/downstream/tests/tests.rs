@@ -0,0 +1,29 @@
+use exported_errors::errors::{self, Error, ParseError};
+use exported_errors::{RootError, Timeout};
+
+#[test]
+fn test_exported_macro_from_crate_root() {
+    fn wait(n: usize) -> Result<(), exported_errors::RootError![Timeout, Cancelled]> {
+        match n {
+            1 => Err(Timeout)?,
+            _ => Ok(()),
+        }
+    }
+
+    assert!(wait(1) == Err(RootError::Timeout(Timeout)));
+}
+
+#[test]
+fn test_exported_macro_from_module() {
+    fn find<'a>(input: &'a [u8]) -> Result<u32, exported_errors::Error!['a; Parse, Utf8, NotFound]> {
+        if input.is_empty() {
+            Err(errors::error::NotFound)?;
+        }
+        let input = std::str::from_utf8(input)?;
+        errors::parse(input).map_err(Error::upcast)
+    }
+
+    assert!(find(b" 42 ") == Ok(42));
+    assert!(find(b"") == Err(Error::NotFound(errors::error::NotFound)));
+    assert!(find(b"x") == Err(Error::Parse(ParseError { unparsed: "x" })));
+}
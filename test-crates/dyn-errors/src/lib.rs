@@ -0,0 +1,40 @@
+//! Errors that cross an `anyhow` or `eyre` boundary, recovered with the `anyhow` and `eyre`
+//! features of powerset-enum.
+use std::fmt;
+
+use powerset_enum::powerset_enum;
+
+#[derive(Debug, PartialEq)]
+pub struct NotFound;
+#[derive(Debug, PartialEq)]
+pub struct Timeout;
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "not found")
+    }
+}
+impl std::error::Error for NotFound {}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "timed out")
+    }
+}
+impl std::error::Error for Timeout {}
+
+#[powerset_enum(export)]
+#[derive(Debug, PartialEq)]
+pub enum FetchError {
+    NotFound(NotFound),
+    Timeout(Timeout),
+}
+
+pub fn fetch(key: &str) -> anyhow::Result<u32> {
+    match key {
+        "missing" => Err(NotFound.into()),
+        "slow" => Err(Timeout.into()),
+        "broken" => Err(anyhow::anyhow!("broken")),
+        _ => Ok(42),
+    }
+}
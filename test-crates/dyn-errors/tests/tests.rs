@@ -0,0 +1,29 @@
+use dyn_errors::*;
+
+#[test]
+fn test_anyhow() {
+    let error = fetch("missing").unwrap_err();
+    assert_eq!(<FetchError![NotFound, Timeout]>::try_from_dyn(error).unwrap(), FetchError::NotFound(NotFound));
+
+    let error = fetch("slow").unwrap_err();
+    let error = <FetchError![NotFound]>::try_from_dyn(error).unwrap_err();
+    assert_eq!(error.to_string(), "timed out");
+
+    let error = fetch("broken").unwrap_err();
+    assert!(<FetchError![NotFound, Timeout]>::try_from_dyn(error).is_err());
+
+    let error: FetchError![NotFound, Timeout] = Timeout.into();
+    let error: anyhow::Error = error.into_dyn();
+    assert!(error.is::<Timeout>());
+}
+
+#[test]
+fn test_eyre() {
+    let error: FetchError![NotFound, Timeout] = NotFound.into();
+    let report: eyre::Report = error.into_dyn();
+    assert_eq!(report.to_string(), "not found");
+    assert_eq!(<FetchError![NotFound, Timeout]>::try_from_dyn(report).unwrap(), FetchError::NotFound(NotFound));
+
+    let report = eyre::eyre!("unrelated");
+    assert!(<FetchError![Timeout]>::try_from_dyn(report).is_err());
+}
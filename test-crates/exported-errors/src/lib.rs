@@ -0,0 +1,34 @@
+//! Powerset enums whose subset macros are exported, for testing them from another crate.
+
+use powerset_enum::*;
+
+#[derive(Debug, PartialEq)]
+pub struct Timeout;
+
+#[powerset_enum(export)]
+#[derive(Debug, PartialEq)]
+pub enum RootError {
+    Timeout(Timeout),
+    Cancelled,
+}
+
+pub mod errors {
+    use powerset_enum::*;
+
+    #[derive(Debug, PartialEq)]
+    pub struct ParseError<'a> {
+        pub unparsed: &'a str,
+    }
+
+    #[powerset_enum(export = "errors")]
+    #[derive(Debug, PartialEq)]
+    pub enum Error<'a> {
+        Parse(ParseError<'a>),
+        Utf8(std::str::Utf8Error),
+        NotFound,
+    }
+
+    pub fn parse(input: &str) -> Result<u32, Error!['_; Parse]> {
+        input.trim().parse().map_err(|_| ParseError { unparsed: input }.into())
+    }
+}
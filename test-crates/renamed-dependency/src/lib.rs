@@ -0,0 +1,6 @@
+//! Uses powerset-enum under a different name, and through a re-export.
+
+/// A facade that re-exports powerset-enum, like an internal errors crate would.
+pub mod facade {
+    pub use pe as powerset;
+}
@@ -0,0 +1,71 @@
+#![allow(clippy::type_complexity)]
+
+use pe::{powerset_enum, Extract};
+use renamed_dependency::facade;
+
+#[derive(Debug, PartialEq)]
+pub struct Exception1;
+#[derive(Debug, PartialEq)]
+pub struct Exception2;
+
+#[powerset_enum(crate = "pe")]
+#[derive(Debug, PartialEq)]
+enum RenamedError<'a> {
+    Exception1(Exception1),
+    Exception2(Exception2),
+    Borrowed(&'a str),
+}
+
+#[facade::powerset::powerset_enum(crate = "renamed_dependency::facade::powerset", export)]
+#[derive(Debug, PartialEq)]
+pub enum FacadeError {
+    Exception1(Exception1),
+    Exception2(Exception2),
+    Cancelled,
+}
+
+#[test]
+fn test_renamed_crate() {
+    fn foo<'a>(n: usize, input: &'a str) -> Result<(), RenamedError!['a; Exception1, Borrowed]> {
+        match n {
+            1 => Err(Exception1)?,
+            _ => Err(input)?,
+        }
+    }
+
+    fn bar<'a>(n: usize, input: &'a str) -> Result<(), RenamedError!['a; Exception1, Exception2, Borrowed, pe::Never]> {
+        foo(n, input).map_err(RenamedError::upcast)?;
+        Err(Exception2)?
+    }
+
+    assert!(bar(1, "x") == Err(RenamedError::Exception1(Exception1)));
+    assert!(bar(2, "x").extract::<&str>() == Err("x"));
+}
+
+#[test]
+fn test_throws_with_renamed_crate() {
+    #[pe::throws(crate = "pe", RenamedError: 'static; Exception1)]
+    fn foo() {
+        Err(Exception1)?;
+    }
+
+    #[pe::throws(crate = "pe", RenamedError: 'static; Exception1, Exception2)]
+    fn bar() {
+        foo()?;
+    }
+
+    assert!(bar() == Err(RenamedError::Exception1(Exception1)));
+}
+
+#[test]
+fn test_reexported_crate() {
+    fn foo(n: usize) -> Result<(), FacadeError![Exception1, Cancelled]> {
+        match n {
+            1 => Err(Exception1)?,
+            _ => Err(facade_error::Cancelled)?,
+        }
+    }
+
+    assert!(foo(1).extract::<Exception1>() == Err(Exception1));
+    assert!(foo(2) == Err(FacadeError::Cancelled(facade_error::Cancelled)));
+}
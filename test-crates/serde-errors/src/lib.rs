@@ -0,0 +1,20 @@
+//! Errors sent across an RPC boundary, serialized with the `serde` feature of powerset-enum.
+use std::borrow::Cow;
+
+use powerset_enum::powerset_enum;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct NotFound {
+    pub key: String,
+}
+
+#[powerset_enum(serde, export)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum RpcError<'a, B> {
+    NotFound(NotFound),
+    Timeout,
+    Rejected { reason: String },
+    Backend(B),
+    Borrowed(Cow<'a, str>),
+}
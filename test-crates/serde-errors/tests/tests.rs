@@ -0,0 +1,46 @@
+use std::borrow::Cow;
+
+use serde_errors::{rpc_error, NotFound, RpcError};
+
+type Full = RpcError!['static, u32; NotFound, Timeout, Rejected, Borrowed];
+// Variants holding the `enum`'s own type parameters can't be excluded with `Never` here, because it
+// doesn't implement `Serialize` and `Deserialize`.
+type Lookup = RpcError!['static, u32; NotFound, Timeout];
+
+#[test]
+fn test_serialize_by_variant_name() {
+    let error: Lookup = NotFound { key: "x".to_owned() }.into();
+    assert_eq!(serde_json::to_string(&error).unwrap(), r#"{"NotFound":{"key":"x"}}"#);
+
+    let error: Full = RpcError::Backend(7);
+    assert_eq!(serde_json::to_string(&error).unwrap(), r#"{"Backend":7}"#);
+
+    let error: Full = rpc_error::Rejected { reason: "no".to_owned() }.into();
+    assert_eq!(serde_json::to_string(&error).unwrap(), r#"{"Rejected":{"reason":"no"}}"#);
+}
+
+#[test]
+fn test_deserialize_into_subset() {
+    let error: Lookup = serde_json::from_str(r#"{"Timeout":null}"#).unwrap();
+    assert_eq!(error, RpcError::Timeout(rpc_error::Timeout));
+
+    let error: Full = serde_json::from_str(r#"{"Borrowed":"text"}"#).unwrap();
+    assert_eq!(error, RpcError::Borrowed(Cow::Borrowed("text")));
+
+    let error: Full = rpc_error::Rejected { reason: "no".to_owned() }.into();
+    let round_trip: Full = serde_json::from_str(&serde_json::to_string(&error).unwrap()).unwrap();
+    assert_eq!(round_trip, error);
+}
+
+#[test]
+fn test_deserialize_variant_outside_subset() {
+    let error = serde_json::from_str::<Lookup>(r#"{"Rejected":{"reason":"no"}}"#).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("unknown variant `Rejected`, expected one of `NotFound`, `Timeout`, `Backend`"));
+
+    let error = serde_json::from_str::<Lookup>(r#"{"Unknown":null}"#).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("unknown variant `Unknown`, expected one of `NotFound`, `Timeout`, `Backend`"));
+}
@@ -0,0 +1,52 @@
+//! Handlers of a web service, whose errors are mapped to HTTP statuses with the `axum` feature of
+//! powerset-enum.
+use std::fmt;
+
+use axum::extract::Path;
+use axum::routing::get;
+use axum::{Json, Router};
+use powerset_enum::powerset_enum;
+use serde_derive::Serialize;
+
+#[derive(Debug)]
+pub struct DbError(pub String);
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "database error: {}", self.0)
+    }
+}
+
+#[powerset_enum(error, axum, export)]
+#[derive(Debug)]
+pub enum ApiError {
+    #[powerset(status = 404)]
+    NotFound,
+    #[powerset(status = 401)]
+    Unauthorized,
+    Db(DbError),
+}
+
+#[derive(Serialize)]
+pub struct User {
+    pub name: String,
+}
+
+async fn get_user(Path(name): Path<String>) -> Result<Json<User>, ApiError![NotFound, Unauthorized, Db]> {
+    match name.as_str() {
+        "missing" => Err(api_error::NotFound.into()),
+        "secret" => Err(api_error::Unauthorized.into()),
+        "broken" => Err(DbError("connection lost".to_owned()).into()),
+        _ => Ok(Json(User { name })),
+    }
+}
+
+async fn get_health() -> Result<&'static str, ApiError![Db]> {
+    Ok("ok")
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/users/{name}", get(get_user))
+        .route("/health", get(get_health))
+}
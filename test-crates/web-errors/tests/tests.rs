@@ -0,0 +1,35 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use tower::ServiceExt;
+
+use web_errors::*;
+
+async fn get(uri: &str) -> (StatusCode, String) {
+    let response = router()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn test_statuses() {
+    assert_eq!(get("/users/alice").await, (StatusCode::OK, r#"{"name":"alice"}"#.to_owned()));
+    assert_eq!(get("/users/missing").await, (StatusCode::NOT_FOUND, "NotFound".to_owned()));
+    assert_eq!(get("/users/secret").await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        get("/users/broken").await,
+        (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error".to_owned()),
+    );
+    assert_eq!(get("/health").await, (StatusCode::OK, "ok".to_owned()));
+}
+
+#[test]
+fn test_status() {
+    let error: ApiError![NotFound, Db] = web_errors::api_error::NotFound.into();
+    assert_eq!(error.status(), StatusCode::NOT_FOUND);
+    let error: ApiError![NotFound, Db] = DbError("timeout".to_owned()).into();
+    assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}